@@ -1,11 +1,40 @@
 #[derive(clap::Args)]
-pub struct FromArgs {}
+pub struct FromArgs {
+  #[clap(subcommand)]
+  pub command: Option<Command>,
+}
 
 impl gravity::config::FromArgs for FromArgs {}
 
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum Command {
+  /// Serve the agent over websocket without the UI
+  Serve,
+  /// Embed stored messages and files that have no embedding
+  Reembed {
+    /// Recompute all embeddings instead of only missing ones
+    #[clap(long, action)]
+    all: bool,
+  },
+  /// Store a file and embed its title and description
+  AddFile {
+    /// File to store
+    path: std::path::PathBuf,
+    /// Title instead of the file name
+    #[clap(long)]
+    title: Option<String>,
+    /// Description searched and embedded along with the title
+    #[clap(long, default_value = "")]
+    description: String,
+  },
+}
+
 #[derive(Default, serde::Deserialize)]
 pub struct FromEnv {
+  #[serde(default)]
   pub db: nebulon::config::ClientConfig,
+  #[serde(default)]
+  pub server: ServerConfig,
 }
 
 impl gravity::config::FromEnv for FromEnv {}
@@ -16,13 +45,33 @@ impl gravity::config::FromEnv for FromEnv {}
 pub struct FromFile {
   /// Orbitus UI config
   pub ui: orbitus::config::UiConfig,
+  /// Model config
+  pub model: Option<ModelConfig>,
+  /// Embedding model config
+  pub embedding: Option<EmbeddingConfig>,
+  /// Sampling config
+  pub sampling: Option<SamplingConfig>,
+  /// Stopping config
+  pub stopping: Option<StoppingConfig>,
+  /// Prompt config
+  pub prompt: Option<PromptConfig>,
+  /// Retrieval config
+  pub retrieval: Option<RetrievalConfig>,
 }
 
 impl gravity::config::FromFile for FromFile {}
 
 #[derive(Clone)]
 pub struct Config {
+  pub command: Option<Command>,
   pub db: nebulon::config::ClientConfig,
+  pub server: ServerConfig,
+  pub model: ModelConfig,
+  pub embedding: EmbeddingConfig,
+  pub sampling: SamplingConfig,
+  pub stopping: StoppingConfig,
+  pub prompt: PromptConfig,
+  pub retrieval: RetrievalConfig,
   pub ui: orbitus::config::UiConfig,
 }
 
@@ -31,20 +80,330 @@ impl gravity::config::Values for Config {
   type TEnv = FromEnv;
   type TFile = FromFile;
 
-  fn new(_: Self::TArgs, env: Self::TEnv) -> Self {
+  fn new(args: Self::TArgs, env: Self::TEnv) -> Self {
     Self {
+      command: args.command,
       db: env.db,
+      server: env.server,
+      model: Default::default(),
+      embedding: Default::default(),
+      sampling: Default::default(),
+      stopping: Default::default(),
+      prompt: Default::default(),
+      retrieval: Default::default(),
       ui: Default::default(),
     }
   }
 
   fn import(&mut self, file: Self::TFile) {
     self.ui = file.ui;
+    if let Some(model) = file.model {
+      self.model = model;
+    }
+    if let Some(embedding) = file.embedding {
+      self.embedding = embedding;
+    }
+    if let Some(sampling) = file.sampling {
+      self.sampling = sampling;
+    }
+    if let Some(stopping) = file.stopping {
+      self.stopping = stopping;
+    }
+    if let Some(prompt) = file.prompt {
+      self.prompt = prompt;
+    }
+    if let Some(retrieval) = file.retrieval {
+      self.retrieval = retrieval;
+    }
   }
 
   fn export(&self) -> Self::TFile {
     Self::TFile {
       ui: self.ui.clone(),
+      model: Some(self.model.clone()),
+      embedding: Some(self.embedding.clone()),
+      sampling: Some(self.sampling.clone()),
+      stopping: Some(self.stopping.clone()),
+      prompt: Some(self.prompt.clone()),
+      retrieval: Some(self.retrieval.clone()),
+    }
+  }
+}
+
+#[derive(derivative::Derivative, Clone, Debug, serde::Deserialize)]
+#[derivative(Default)]
+pub struct ServerConfig {
+  #[derivative(Default(value = "\"localhost\".to_string()"))]
+  pub host: String,
+  #[derivative(Default(value = "5000"))]
+  pub port: u16,
+}
+
+#[derive(
+  derivative::Derivative,
+  Clone,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[derivative(Default)]
+#[serde(default)]
+pub struct ModelConfig {
+  /// Model architecture of the weights
+  pub architecture: ModelArchitecture,
+  /// End of sequence token or the architecture default when not set
+  pub eos_token: Option<String>,
+  /// Local GGUF weights path
+  pub weights: Option<std::path::PathBuf>,
+  /// Local tokenizer path
+  pub tokenizer: Option<std::path::PathBuf>,
+  /// Download files missing locally from the Hugging Face hub
+  #[derivative(Default(value = "true"))]
+  pub download: bool,
+  /// Hugging Face hub repository to download from
+  #[derivative(Default(value = "\"lmz/candle-quantized-phi\".to_string()"))]
+  pub repo: String,
+  /// GGUF weights file name in the hub repository
+  #[derivative(Default(value = "\"model-v2-q4k.gguf\".to_string()"))]
+  pub repo_weights: String,
+  /// Tokenizer file name in the hub repository
+  #[derivative(Default(value = "\"tokenizer.json\".to_string()"))]
+  pub repo_tokenizer: String,
+}
+
+#[derive(
+  derivative::Derivative,
+  Clone,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[derivative(Default)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+  /// Embed messages and files for semantic retrieval
+  #[derivative(Default(value = "true"))]
+  pub enabled: bool,
+  /// Local BERT config path
+  pub config: Option<std::path::PathBuf>,
+  /// Local safetensors weights path
+  pub weights: Option<std::path::PathBuf>,
+  /// Local tokenizer path
+  pub tokenizer: Option<std::path::PathBuf>,
+  /// Download files missing locally from the Hugging Face hub
+  #[derivative(Default(value = "true"))]
+  pub download: bool,
+  /// Hugging Face hub repository to download from
+  #[derivative(Default(
+    value = "\"sentence-transformers/all-MiniLM-L6-v2\".to_string()"
+  ))]
+  pub repo: String,
+  /// BERT config file name in the hub repository
+  #[derivative(Default(value = "\"config.json\".to_string()"))]
+  pub repo_config: String,
+  /// Safetensors weights file name in the hub repository
+  #[derivative(Default(value = "\"model.safetensors\".to_string()"))]
+  pub repo_weights: String,
+  /// Tokenizer file name in the hub repository
+  #[derivative(Default(value = "\"tokenizer.json\".to_string()"))]
+  pub repo_tokenizer: String,
+}
+
+#[derive(
+  Default,
+  Clone,
+  Copy,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelArchitecture {
+  /// Quantized phi-2
+  #[default]
+  Phi2,
+  /// Quantized llama
+  Llama,
+  /// Quantized mistral
+  Mistral,
+}
+
+impl ModelArchitecture {
+  pub fn eos_token(&self) -> &'static str {
+    match self {
+      Self::Phi2 => "<|endoftext|>",
+      Self::Llama | Self::Mistral => "</s>",
     }
   }
 }
+
+#[derive(
+  derivative::Derivative,
+  Clone,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[derivative(Default)]
+#[serde(default)]
+pub struct SamplingConfig {
+  /// Sampling temperature or greedy sampling when not set
+  pub temperature: Option<f64>,
+  /// Nucleus sampling probability cutoff
+  pub top_p: Option<f64>,
+  /// Number of most likely tokens to sample from
+  pub top_k: Option<usize>,
+  /// Sampling seed or random seed per prompt when not set
+  pub seed: Option<u64>,
+  /// Penalty for repeating recent tokens
+  #[derivative(Default(value = "1.0"))]
+  pub repeat_penalty: f32,
+  /// Number of recent tokens the repeat penalty applies to
+  #[derivative(Default(value = "64"))]
+  pub repeat_last_n: usize,
+}
+
+impl SamplingConfig {
+  pub fn with_override(&self, sampling: &gravity::SamplingOverride) -> Self {
+    Self {
+      temperature: sampling.temperature.or(self.temperature),
+      top_p: sampling.top_p.or(self.top_p),
+      top_k: sampling.top_k.or(self.top_k),
+      seed: sampling.seed.or(self.seed),
+      repeat_penalty: sampling.repeat_penalty.unwrap_or(self.repeat_penalty),
+      repeat_last_n: sampling.repeat_last_n.unwrap_or(self.repeat_last_n),
+    }
+  }
+}
+
+#[derive(
+  derivative::Derivative,
+  Clone,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[derivative(Default)]
+#[serde(default)]
+pub struct StoppingConfig {
+  /// Maximum number of tokens generated per prompt
+  #[derivative(Default(value = "512"))]
+  pub max_new_tokens: usize,
+  /// Strings that stop generation when generated
+  pub stop: Vec<String>,
+}
+
+#[derive(
+  Default,
+  Clone,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[serde(default)]
+pub struct PromptConfig {
+  /// Template the conversation is rendered with
+  pub template: PromptTemplate,
+  /// System prompt at the start of the conversation
+  pub system: Option<String>,
+}
+
+#[derive(
+  Default,
+  Clone,
+  Copy,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptTemplate {
+  /// Only the last prompt without history, system prompt or retrieved records
+  Raw,
+  /// Phi "Instruct:" and "Output:" turns
+  #[default]
+  Phi,
+  /// ChatML "<|im_start|>" and "<|im_end|>" turns
+  ChatMl,
+  /// Mistral "[INST]" and "[/INST]" turns
+  Mistral,
+}
+
+impl PromptTemplate {
+  /// Whether the system prompt and the records retrieved into it are rendered
+  pub fn renders_system(&self) -> bool {
+    !matches!(self, Self::Raw)
+  }
+
+  pub fn stop(&self) -> &'static [&'static str] {
+    match self {
+      Self::Raw => &[],
+      Self::Phi => &["\nInstruct:"],
+      Self::ChatMl => &["<|im_end|>"],
+      Self::Mistral => &["[INST]"],
+    }
+  }
+}
+
+#[derive(
+  derivative::Derivative,
+  Clone,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[derivative(Default)]
+#[serde(default)]
+pub struct RetrievalConfig {
+  /// How past records relevant to the prompt are found
+  pub mode: RetrievalMode,
+  /// Maximum number of records added to the prompt
+  #[derivative(Default(value = "4"))]
+  pub limit: usize,
+  /// Maximum number of tokens records take in the prompt
+  #[derivative(Default(value = "256"))]
+  pub budget: usize,
+  /// Also retrieve agent memos
+  #[derivative(Default(value = "true"))]
+  pub memos: bool,
+}
+
+#[derive(
+  Default,
+  Clone,
+  Copy,
+  Debug,
+  serde::Serialize,
+  serde::Deserialize,
+  schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RetrievalMode {
+  /// No retrieval
+  Off,
+  /// Full text search only
+  Lexical,
+  /// Vector search only
+  Semantic,
+  /// Vector and full text search fused by rank
+  #[default]
+  Hybrid,
+}
+
+impl RetrievalMode {
+  pub fn lexical(&self) -> bool {
+    matches!(self, Self::Lexical | Self::Hybrid)
+  }
+
+  pub fn semantic(&self) -> bool {
+    matches!(self, Self::Semantic | Self::Hybrid)
+  }
+}
@@ -0,0 +1,269 @@
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+const REEMBED_BATCH: usize = 32;
+
+/// Sentence embedding model producing normalized mean pooled vectors
+pub struct Embedder {
+  model: BertModel,
+  tokenizer: Tokenizer,
+  device: Device,
+  dimension: usize,
+}
+
+impl Embedder {
+  pub fn new(
+    config: &super::config::EmbeddingConfig,
+    device: &Device,
+  ) -> anyhow::Result<Self> {
+    #[derive(serde::Deserialize)]
+    struct Dimension {
+      hidden_size: usize,
+    }
+
+    let model_config = super::model::resolve_file(
+      config.config.as_ref(),
+      config.download,
+      config.repo.as_str(),
+      config.repo_config.as_str(),
+    )?;
+    let tokenizer = super::model::resolve_file(
+      config.tokenizer.as_ref(),
+      config.download,
+      config.repo.as_str(),
+      config.repo_tokenizer.as_str(),
+    )?;
+    let weights = super::model::resolve_file(
+      config.weights.as_ref(),
+      config.download,
+      config.repo.as_str(),
+      config.repo_weights.as_str(),
+    )?;
+
+    let model_config = std::fs::read_to_string(model_config)?;
+    let dimension =
+      serde_json::from_str::<Dimension>(&model_config)?.hidden_size;
+    let model_config = serde_json::from_str::<BertConfig>(&model_config)?;
+
+    let mut tokenizer = match Tokenizer::from_file(tokenizer) {
+      Ok(tokenizer) => tokenizer,
+      Err(_err) => return Err(anyhow::anyhow!("Failed getting tokenizer")),
+    };
+    tokenizer.with_padding(Some(PaddingParams::default()));
+    if let Err(err) =
+      tokenizer.with_truncation(Some(TruncationParams::default()))
+    {
+      return Err(anyhow::anyhow!("Failed setting truncation: {err}"));
+    }
+
+    let vb = VarBuilder::from_buffered_safetensors(
+      std::fs::read(weights)?,
+      DType::F32,
+      device,
+    )?;
+    let model = BertModel::load(vb, &model_config)?;
+
+    Ok(Self {
+      model,
+      tokenizer,
+      device: device.clone(),
+      dimension,
+    })
+  }
+
+  pub fn dimension(&self) -> usize {
+    self.dimension
+  }
+
+  pub fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+    if texts.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let encodings = match self.tokenizer.encode_batch(texts, true) {
+      Ok(encodings) => encodings,
+      Err(_err) => return Err(anyhow::anyhow!("Tokenizer output bad")),
+    };
+
+    let ids = encodings
+      .iter()
+      .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+      .collect::<candle_core::Result<Vec<_>>>()?;
+    let ids = Tensor::stack(&ids, 0)?;
+    let mask = encodings
+      .iter()
+      .map(|encoding| {
+        Tensor::new(encoding.get_attention_mask(), &self.device)
+      })
+      .collect::<candle_core::Result<Vec<_>>>()?;
+    let mask = Tensor::stack(&mask, 0)?;
+    let token_type_ids = ids.zeros_like()?;
+
+    let output = self.model.forward(&ids, &token_type_ids, Some(&mask))?;
+
+    let mask = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+    let pooled = output
+      .broadcast_mul(&mask)?
+      .sum(1)?
+      .broadcast_div(&mask.sum(1)?)?;
+    let normalized =
+      pooled.broadcast_div(&pooled.sqr()?.sum_keepdim(1)?.sqrt()?)?;
+
+    Ok(normalized.to_vec2::<f32>()?)
+  }
+
+  pub fn embed_one(&self, text: String) -> anyhow::Result<Vec<f32>> {
+    self
+      .embed(vec![text])?
+      .into_iter()
+      .next()
+      .ok_or_else(|| anyhow::anyhow!("Embedder returned none"))
+  }
+
+  pub fn check(&self, db: &nebulon::client::Client) -> anyhow::Result<()> {
+    if self.dimension != db.embedding_dimension() {
+      return Err(anyhow::anyhow!(
+        "Embedding model has {} dimensions but database expects {}",
+        self.dimension,
+        db.embedding_dimension()
+      ));
+    }
+
+    Ok(())
+  }
+}
+
+/// Load the embedder unless it is disabled, fails loading or doesn't match
+/// the database so the agent can still answer without semantic retrieval
+pub fn load(
+  config: &super::config::EmbeddingConfig,
+  device: &Device,
+  db: &nebulon::client::Client,
+) -> Option<Embedder> {
+  if !config.enabled {
+    tracing::info!("Embedding disabled");
+    return None;
+  }
+
+  match Embedder::new(config, device).and_then(|embedder| {
+    embedder.check(db)?;
+    Ok(embedder)
+  }) {
+    Ok(embedder) => Some(embedder),
+    Err(err) => {
+      tracing::warn!("Running without embeddings because {err}");
+      None
+    }
+  }
+}
+
+/// Embed and index a message logging failures instead of returning them
+pub async fn index_message(
+  embedder: Option<&Embedder>,
+  db: &nebulon::client::Client,
+  message: String,
+  content: String,
+) -> Option<Vec<f32>> {
+  let embedding = match embedder?.embed_one(content) {
+    Ok(embedding) => embedding,
+    Err(err) => {
+      tracing::warn!("Failed embedding message {message}: {err}");
+      return None;
+    }
+  };
+  if let Err(err) = db
+    .set_message_embedding(message.clone(), embedding.clone())
+    .await
+  {
+    tracing::warn!("Failed indexing message {message}: {err}");
+  }
+
+  Some(embedding)
+}
+
+/// Embed and index a file by its title and description logging failures
+pub async fn index_file(
+  embedder: Option<&Embedder>,
+  db: &nebulon::client::Client,
+  file: &nebulon::client::File,
+) -> Option<Vec<f32>> {
+  let embedding = match embedder?.embed_one(file_text(file)) {
+    Ok(embedding) => embedding,
+    Err(err) => {
+      tracing::warn!("Failed embedding file {}: {err}", file.id);
+      return None;
+    }
+  };
+  if let Err(err) = db
+    .set_file_embedding(file.id.clone(), embedding.clone())
+    .await
+  {
+    tracing::warn!("Failed indexing file {}: {err}", file.id);
+  }
+
+  Some(embedding)
+}
+
+pub fn file_text(file: &nebulon::client::File) -> String {
+  format!("{}\n{}", file.title, file.description)
+}
+
+#[tokio::main]
+pub async fn reembed(
+  config: super::config::Config,
+  all: bool,
+) -> anyhow::Result<()> {
+  if !config.embedding.enabled {
+    return Err(anyhow::anyhow!("Embedding is disabled"));
+  }
+
+  let device = Device::Cpu;
+  let embedder = Embedder::new(&config.embedding, &device)?;
+
+  let db = super::connect(&config.db).await?;
+  embedder.check(&db)?;
+
+  if all {
+    db.clear_embeddings().await?;
+  }
+
+  let mut messages = 0usize;
+  loop {
+    let batch = db.list_unembedded_messages(REEMBED_BATCH).await?;
+    if batch.is_empty() {
+      break;
+    }
+
+    let embeddings = embedder.embed(
+      batch
+        .iter()
+        .map(|message| message.content.clone())
+        .collect::<Vec<_>>(),
+    )?;
+    for (message, embedding) in batch.into_iter().zip(embeddings) {
+      db.set_message_embedding(message.id, embedding).await?;
+      messages = messages.saturating_add(1);
+    }
+  }
+
+  let mut files = 0usize;
+  loop {
+    let batch = db.list_unembedded_files(REEMBED_BATCH).await?;
+    if batch.is_empty() {
+      break;
+    }
+
+    let embeddings =
+      embedder.embed(batch.iter().map(file_text).collect::<Vec<_>>())?;
+    for (file, embedding) in batch.into_iter().zip(embeddings) {
+      db.set_file_embedding(file.id, embedding).await?;
+      files = files.saturating_add(1);
+    }
+  }
+
+  tracing::info!("Embedded {messages} messages and {files} files");
+
+  Ok(())
+}
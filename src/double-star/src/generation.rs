@@ -0,0 +1,217 @@
+use candle_core::{DType, Device, Tensor};
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use tokenizers::Tokenizer;
+
+/// Feeds tokens to a model one chunk at a time reusing its KV cache
+pub struct Decoder<'a> {
+  model: &'a mut dyn super::model::TextModel,
+  device: &'a Device,
+  position: usize,
+}
+
+impl<'a> Decoder<'a> {
+  pub fn new(
+    model: &'a mut dyn super::model::TextModel,
+    device: &'a Device,
+  ) -> Self {
+    model.clear_kv_cache();
+    Self {
+      model,
+      device,
+      position: 0,
+    }
+  }
+
+  /// Forward tokens the model hasn't seen yet and return next token logits
+  pub fn forward(&mut self, tokens: &[u32]) -> anyhow::Result<Tensor> {
+    let input = Tensor::new(tokens, self.device)?.unsqueeze(0)?;
+    tracing::debug!("input {}", input);
+
+    let logits = self.model.forward(&input, self.position)?;
+    tracing::debug!("logits {}", logits);
+    self.position = self.position.saturating_add(tokens.len());
+
+    let processed = logits.to_dtype(DType::F32)?.squeeze(0)?;
+    tracing::debug!("processed logits {}", processed);
+
+    Ok(processed)
+  }
+
+  /// Number of tokens the model has seen so far
+  pub fn position(&self) -> usize {
+    self.position
+  }
+}
+
+/// Picks next tokens from logits according to sampling config
+pub struct Sampler {
+  processor: LogitsProcessor,
+  repeat_penalty: f32,
+  repeat_last_n: usize,
+}
+
+impl Sampler {
+  pub fn new(config: &super::config::SamplingConfig) -> Self {
+    let sampling = match (config.temperature, config.top_k, config.top_p) {
+      (None, _, _) => Sampling::ArgMax,
+      (Some(temperature), _, _) if temperature <= 0.0 => Sampling::ArgMax,
+      (Some(temperature), None, None) => Sampling::All { temperature },
+      (Some(temperature), Some(k), None) => Sampling::TopK { k, temperature },
+      (Some(temperature), None, Some(p)) => Sampling::TopP { p, temperature },
+      (Some(temperature), Some(k), Some(p)) => {
+        Sampling::TopKThenTopP { k, p, temperature }
+      }
+    };
+    let seed = config.seed.unwrap_or_else(rand::random);
+
+    Self {
+      processor: LogitsProcessor::from_sampling(seed, sampling),
+      repeat_penalty: config.repeat_penalty,
+      repeat_last_n: config.repeat_last_n,
+    }
+  }
+
+  /// Sample the next token given all tokens so far
+  pub fn sample(
+    &mut self,
+    logits: &Tensor,
+    tokens: &[u32],
+  ) -> anyhow::Result<u32> {
+    let start = tokens.len().saturating_sub(self.repeat_last_n);
+    let recent = tokens.get(start..).unwrap_or_default();
+    if self.repeat_penalty == 1.0 || recent.is_empty() {
+      return Ok(self.processor.sample(logits)?);
+    }
+
+    let logits = candle_transformers::utils::apply_repeat_penalty(
+      logits,
+      self.repeat_penalty,
+      recent,
+    )?;
+    Ok(self.processor.sample(&logits)?)
+  }
+}
+
+/// Holds back streamed text that could still turn into a stop sequence
+pub struct Stopper {
+  stop: Vec<String>,
+  pending: String,
+}
+
+impl Stopper {
+  pub fn new(stop: Vec<String>) -> Self {
+    Self {
+      stop: stop.into_iter().filter(|stop| !stop.is_empty()).collect(),
+      pending: String::new(),
+    }
+  }
+
+  /// Push generated text and return text safe to emit with the matched stop
+  pub fn push(&mut self, text: &str) -> (String, Option<String>) {
+    self.pending.push_str(text);
+
+    let matched = self
+      .stop
+      .iter()
+      .filter_map(|stop| self.pending.find(stop.as_str()).map(|at| (at, stop)))
+      .min_by_key(|(at, _)| *at);
+    if let Some((at, stop)) = matched {
+      let stop = stop.clone();
+      let mut emitted = std::mem::take(&mut self.pending);
+      emitted.truncate(at);
+      return (emitted, Some(stop));
+    }
+
+    let held = self
+      .pending
+      .char_indices()
+      .map(|(at, _)| at)
+      .find(|at| {
+        let suffix = self.pending.get(*at..).unwrap_or_default();
+        self.stop.iter().any(|stop| stop.starts_with(suffix))
+      })
+      .unwrap_or(self.pending.len());
+    let held = self.pending.split_off(held);
+    let emitted = std::mem::replace(&mut self.pending, held);
+
+    (emitted, None)
+  }
+
+  /// Return the text held back when generation stopped for another reason
+  pub fn finish(&mut self) -> String {
+    std::mem::take(&mut self.pending)
+  }
+}
+
+/// Ids of stop sequences that are special tokens of `tokenizer`
+///
+/// Special tokens are skipped when decoding so their text has to be pushed to
+/// the [`Stopper`] when they are generated.
+pub fn special_stop_tokens(
+  tokenizer: &Tokenizer,
+  stop: &[String],
+) -> std::collections::HashMap<u32, String> {
+  tokenizer
+    .get_added_tokens_decoder()
+    .into_iter()
+    .filter(|(_, token)| token.special && stop.contains(&token.content))
+    .map(|(id, token)| (id, token.content))
+    .collect()
+}
+
+/// Decodes generated tokens into text as soon as the text is complete
+pub struct Detokenizer<'a> {
+  tokenizer: &'a Tokenizer,
+  tokens: Vec<u32>,
+  emitted: usize,
+  decoded: usize,
+}
+
+impl<'a> Detokenizer<'a> {
+  pub fn new(tokenizer: &'a Tokenizer) -> Self {
+    Self {
+      tokenizer,
+      tokens: Vec::new(),
+      emitted: 0,
+      decoded: 0,
+    }
+  }
+
+  /// Push a generated token and return newly completed text
+  pub fn push(&mut self, token: u32) -> anyhow::Result<String> {
+    let previous = self.decode(self.emitted, self.decoded)?;
+    self.tokens.push(token);
+    let current = self.decode(self.emitted, self.tokens.len())?;
+
+    // NOTE: incomplete UTF-8 sequences decode to the replacement character
+    if current.len() <= previous.len() || current.ends_with('\u{FFFD}') {
+      return Ok(String::new());
+    }
+
+    let text = current.get(previous.len()..).unwrap_or_default().to_owned();
+    self.emitted = self.decoded;
+    self.decoded = self.tokens.len();
+    Ok(text)
+  }
+
+  /// Return text of tokens that haven't been emitted yet
+  pub fn finish(&mut self) -> anyhow::Result<String> {
+    let previous = self.decode(self.emitted, self.decoded)?;
+    let current = self.decode(self.emitted, self.tokens.len())?;
+    self.emitted = self.tokens.len();
+    self.decoded = self.tokens.len();
+    Ok(current.get(previous.len()..).unwrap_or_default().to_owned())
+  }
+
+  fn decode(&self, start: usize, end: usize) -> anyhow::Result<String> {
+    let tokens = self.tokens.get(start..end).unwrap_or_default();
+    if tokens.is_empty() {
+      return Ok(String::new());
+    }
+
+    self
+      .tokenizer
+      .decode(tokens, true)
+      .map_err(|err| anyhow::anyhow!("Failed decoding tokens: {err}"))
+  }
+}
@@ -7,21 +7,71 @@
 #![deny(clippy::allow_attributes_without_reason)]
 
 pub mod config;
+pub mod embedding;
+pub mod generation;
+pub mod model;
+pub mod prompt;
+pub mod retrieval;
+pub mod ws;
 
-use candle_core::{DType, Device, Tensor};
-use candle_transformers::generation::LogitsProcessor;
-use candle_transformers::models::mixformer::Config;
-use candle_transformers::models::quantized_mixformer::MixFormerSequentialForCausalLM as QMixFormer;
-use hf_hub::api::sync::Api;
-use hf_hub::Repo;
+use candle_core::Device;
 use tokenizers::Tokenizer;
 
+const USER_SENDER: &str = "user";
+const AGENT_SENDER: &str = "double_star";
+
 #[tokio::main]
 pub async fn run(
   tx: flume::Sender<gravity::DoubleStarMessage>,
   rx: flume::Receiver<gravity::OrbitusMessage>,
-  _config: config::Config,
-  _config_rx: flume::Receiver<gravity::config::ConfigUpdate<config::Config>>,
+  config: config::Config,
+  config_rx: flume::Receiver<gravity::config::ConfigUpdate<config::Config>>,
+) -> anyhow::Result<()> {
+  let db = connect(&config.db).await?;
+  agent(tx, rx, config, config_rx, db).await
+}
+
+/// Store a file and embed its title and description
+#[tokio::main]
+pub async fn add_file(
+  config: config::Config,
+  path: std::path::PathBuf,
+  title: Option<String>,
+  description: String,
+) -> anyhow::Result<()> {
+  let data = std::fs::read(&path)?;
+  let name = |part: Option<&std::ffi::OsStr>| {
+    part.and_then(|part| part.to_str()).unwrap_or_default().to_string()
+  };
+  let title = title.unwrap_or_else(|| name(path.file_stem()));
+  let extension = name(path.extension());
+
+  let db = connect(&config.db).await?;
+  let embedder = embedding::load(&config.embedding, &Device::Cpu, &db);
+  let file = db.insert_file(title, extension, description, data).await?;
+  embedding::index_file(embedder.as_ref(), &db, &file).await;
+  tracing::info!("Added file {}", file.id);
+
+  Ok(())
+}
+
+/// Connect to the database and apply pending migrations
+pub async fn connect(
+  config: &nebulon::config::ClientConfig,
+) -> anyhow::Result<nebulon::client::Client> {
+  let db = nebulon::client::connect(config.clone()).await?;
+  db.migrate().await?;
+
+  Ok(db)
+}
+
+/// Answer prompts until orbitus exits storing the chat in `db`
+pub async fn agent(
+  tx: flume::Sender<gravity::DoubleStarMessage>,
+  rx: flume::Receiver<gravity::OrbitusMessage>,
+  mut config: config::Config,
+  config_rx: flume::Receiver<gravity::config::ConfigUpdate<config::Config>>,
+  db: nebulon::client::Client,
 ) -> anyhow::Result<()> {
   let device = match Device::cuda_if_available(0) {
     Ok(cuda) => {
@@ -34,79 +84,243 @@ pub async fn run(
     }
   };
 
-  let api = Api::new()?;
-  let repo = api.repo(Repo::new(
-    "lmz/candle-quantized-phi".to_string(),
-    hf_hub::RepoType::Model,
-  ));
-  let tokenizer_filename = repo.get("tokenizer.json")?;
-  let tokenizer = match Tokenizer::from_file(tokenizer_filename) {
+  let files = model::resolve(&config.model)?;
+  let tokenizer = match Tokenizer::from_file(files.tokenizer) {
     Ok(tokenizer) => tokenizer,
     Err(_err) => return Err(anyhow::anyhow!("Failed getting tokenizer")),
   };
 
-  let vb =
-    match candle_transformers::quantized_var_builder::VarBuilder::from_gguf(
-      &repo.get("model-v2-q4k.gguf")?,
-      &device,
-    ) {
-      Ok(vars) => vars,
-      Err(_err) => return Err(anyhow::anyhow!("I failed in life")),
-    };
+  let mut model = model::load(&config.model, &files.weights, &device)?;
+  tracing::info!(
+    "Loaded {:?} model with {} tokens vocabulary and {} tokens context",
+    config.model.architecture,
+    model.vocab_size(),
+    model.context_length()
+  );
 
-  let model_config = Config::v2();
-  let mut model = QMixFormer::new_v2(&model_config, vb)?;
+  let embedder = embedding::load(&config.embedding, &device, &db);
 
-  let mut logits_processor = LogitsProcessor::new(rand::random(), None, None);
+  let chat = db.insert_chat().await?;
+  tracing::info!("Started chat {}", chat.id);
 
+  let mut history = Vec::new();
+  let mut queued = std::collections::VecDeque::new();
   loop {
-    let prompt = match rx.recv_async().await? {
+    let message = match queued.pop_front() {
+      Some(message) => message,
+      None => rx.recv_async().await?,
+    };
+    let prompt = match message {
       gravity::OrbitusMessage::Submit(prompt) => prompt,
+      gravity::OrbitusMessage::Cancel => {
+        tracing::debug!("Nothing to cancel");
+        continue;
+      }
       gravity::OrbitusMessage::Exited => {
         break;
       }
     };
 
-    let tokenizer_output = match tokenizer.encode(prompt, true) {
-      Ok(result) => result,
-      Err(_err) => return Err(anyhow::anyhow!("Tokenizer output bad")),
+    while let Ok(update) = config_rx.try_recv() {
+      config = update.config;
+    }
+    let sampling = config.sampling.with_override(&prompt.sampling);
+    let mut sampler = generation::Sampler::new(&sampling);
+
+    let embedding = persist(
+      &db,
+      embedder.as_ref(),
+      &chat.id,
+      USER_SENDER,
+      prompt.content.clone(),
+    )
+    .await;
+
+    // NOTE: retrieved records are added to the system prompt
+    let citations = if config.prompt.template.renders_system() {
+      match retrieval::retrieve(
+        &db,
+        &config.retrieval,
+        &chat.id,
+        &prompt.content,
+        embedding,
+      )
+      .await
+      {
+        Ok(citations) => citations,
+        Err(err) => {
+          tracing::error!("Failed retrieving records: {err}");
+          Vec::new()
+        }
+      }
+    } else {
+      Vec::new()
     };
-    let mut tokens = tokenizer_output.get_ids().to_vec();
+    let citations =
+      retrieval::fit(citations, config.retrieval.budget, |content| {
+        match tokenizer.encode(content, false) {
+          Ok(result) => Ok(result.len()),
+          Err(_err) => Err(anyhow::anyhow!("Tokenizer output bad")),
+        }
+      })?;
+    let system = retrieval::system(config.prompt.system.as_deref(), &citations);
+    if !citations.is_empty() {
+      tracing::debug!("Retrieved {} records", citations.len());
+      tx.send_async(gravity::DoubleStarMessage::Retrieved(citations)).await?;
+    }
+
+    history.push(prompt::Turn {
+      role: prompt::Role::User,
+      content: prompt.content,
+    });
+    let mut tokens = prompt::tokenize(
+      &tokenizer,
+      config.prompt.template,
+      system.as_deref(),
+      &history,
+      model
+        .context_length()
+        .saturating_sub(config.stopping.max_new_tokens)
+        .max(1),
+    )?;
 
-    loop {
-      let input = Tensor::new(tokens.clone(), &device)?.unsqueeze(0)?;
-      tracing::debug!("input {}", input);
+    let eos_token = config
+      .model
+      .eos_token
+      .as_deref()
+      .unwrap_or(config.model.architecture.eos_token());
+    let eos_token = tokenizer.token_to_id(eos_token);
+    if eos_token.is_none() {
+      tracing::warn!("Tokenizer has no end of sequence token");
+    }
 
-      model.clear_kv_cache();
-      let logits = model.forward(&input)?;
-      tracing::debug!("logits {}", logits);
+    let context_length = model.context_length();
+    let mut decoder = generation::Decoder::new(model.as_mut(), &device);
+    let stop = config
+      .stopping
+      .stop
+      .iter()
+      .cloned()
+      .chain(config.prompt.template.stop().iter().map(|x| x.to_string()))
+      .collect::<Vec<_>>();
+    let stop_tokens = generation::special_stop_tokens(&tokenizer, &stop);
+    let mut stopper = generation::Stopper::new(stop);
+    let mut detokenizer = generation::Detokenizer::new(&tokenizer);
+    let mut input = tokens.clone();
+    let mut generated = 0usize;
+    let mut reply = String::new();
+    let mut exited = false;
+    let reason = loop {
+      let mut cancelled = false;
+      for message in rx.try_iter() {
+        match message {
+          gravity::OrbitusMessage::Cancel => cancelled = true,
+          gravity::OrbitusMessage::Exited => exited = true,
+          submit @ gravity::OrbitusMessage::Submit(_) => {
+            queued.push_back(submit);
+          }
+        }
+      }
+      if cancelled || exited {
+        break None;
+      }
 
-      let processed = logits.to_dtype(DType::F32)?.squeeze(0)?;
-      tracing::debug!("processed logits {}", processed);
+      if generated >= config.stopping.max_new_tokens {
+        break Some(gravity::StopReason::MaxNewTokens);
+      }
+      if decoder.position().saturating_add(input.len()) > context_length {
+        break Some(gravity::StopReason::ContextLength);
+      }
 
-      let next_token = logits_processor.sample(&processed)?;
+      let logits = decoder.forward(&input)?;
+      let next_token = sampler.sample(&logits, &tokens)?;
+      generated = generated.saturating_add(1);
+      if Some(next_token) == eos_token {
+        break Some(gravity::StopReason::Eos);
+      }
+      tokens.push(next_token);
+      input = vec![next_token];
 
-      let next_word = match tokenizer.decode(&[next_token], false) {
-        Ok(word) => word,
-        Err(_err) => return Err(anyhow::anyhow!("Next word bad")),
+      let next_text = match stop_tokens.get(&next_token) {
+        Some(stop) => detokenizer.finish()? + stop,
+        None => detokenizer.push(next_token)?,
       };
-      tracing::info!("Generated text: {}", next_word);
+      tracing::debug!("Generated text: {}", next_text);
 
-      tx.send_async(gravity::DoubleStarMessage::Generated(next_word.clone()))
-        .await?;
+      let (text, stop) = stopper.push(&next_text);
+      if !text.is_empty() {
+        reply.push_str(&text);
+        tx.send_async(gravity::DoubleStarMessage::Generated(text)).await?;
+      }
+      if let Some(stop) = stop {
+        break Some(gravity::StopReason::StopSequence(stop));
+      }
+    };
 
-      if next_word == "." {
-        tx.send_async(gravity::DoubleStarMessage::Break).await?;
+    let Some(reason) = reason else {
+      if !reply.is_empty() {
+        persist(&db, embedder.as_ref(), &chat.id, AGENT_SENDER, reply.clone())
+          .await;
+      }
+      // NOTE: partial and empty replies are kept so turns keep alternating
+      history.push(prompt::Turn {
+        role: prompt::Role::Agent,
+        content: reply,
+      });
+      if exited {
         break;
       }
+      tracing::info!("Generation cancelled");
+      tx.send_async(gravity::DoubleStarMessage::Cancelled).await?;
+      continue;
+    };
 
-      let tokenizer_output = match tokenizer.encode(next_word, true) {
-        Ok(result) => result,
-        Err(_err) => return Err(anyhow::anyhow!("Tokenizer output bad")),
-      };
-      tokens.extend(tokenizer_output.get_ids());
+    let text = match reason {
+      gravity::StopReason::StopSequence(_) => String::new(),
+      _ => {
+        let (mut text, stop) = stopper.push(&detokenizer.finish()?);
+        if stop.is_none() {
+          text.push_str(&stopper.finish());
+        }
+        text
+      }
+    };
+    if !text.is_empty() {
+      reply.push_str(&text);
+      tx.send_async(gravity::DoubleStarMessage::Generated(text)).await?;
     }
+    tracing::info!("Generation stopped because {:?}", reason);
+    tx.send_async(gravity::DoubleStarMessage::Break(reason)).await?;
+
+    persist(&db, embedder.as_ref(), &chat.id, AGENT_SENDER, reply.clone())
+      .await;
+    history.push(prompt::Turn {
+      role: prompt::Role::Agent,
+      content: reply,
+    });
   }
 
   Ok(())
 }
+
+/// Store and embed a message logging failures so the agent keeps answering
+async fn persist(
+  db: &nebulon::client::Client,
+  embedder: Option<&embedding::Embedder>,
+  chat: &str,
+  sender: &str,
+  content: String,
+) -> Option<Vec<f32>> {
+  match db
+    .insert_message(chat.to_string(), sender.to_string(), content.clone())
+    .await
+  {
+    Ok(message) => {
+      embedding::index_message(embedder, db, message.id, content).await
+    }
+    Err(err) => {
+      tracing::error!("Failed storing {sender} message: {err}");
+      None
+    }
+  }
+}
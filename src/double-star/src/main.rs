@@ -13,7 +13,25 @@ fn main() -> anyhow::Result<()> {
     env!("ORGANIZATION"),
     "double-star",
     concat!(env!("CARGO_PKG_REPOSITORY"), "/src/double-star"),
-  );
+  )?;
+
+  match config.values().command {
+    Some(double_star::config::Command::Serve) => {
+      return double_star::ws::run(config.values(), config.subscribe());
+    }
+    Some(double_star::config::Command::Reembed { all }) => {
+      return double_star::embedding::reembed(config.values(), all);
+    }
+    Some(double_star::config::Command::AddFile {
+      path,
+      title,
+      description,
+    }) => {
+      return double_star::add_file(config.values(), path, title, description);
+    }
+    None => {}
+  }
+
   let config_values = config.values();
   let orbitus_config_values = config.values();
   let config_rx = config.subscribe();
@@ -36,8 +54,8 @@ fn main() -> anyhow::Result<()> {
   let config_handle = std::thread::spawn(move || {
     while let Ok(new_config) = config_subscriber.recv() {
       if let Err(err) = config.export(double_star::config::Config {
-        db: config.values().db,
         ui: new_config.ui,
+        ..config.values()
       }) {
         tracing::error!("Config error: {}", err);
       }
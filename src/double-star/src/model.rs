@@ -0,0 +1,192 @@
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::models::mixformer::Config as MixFormerConfig;
+use candle_transformers::models::quantized_llama::ModelWeights as QLlama;
+use candle_transformers::models::quantized_mixformer::MixFormerSequentialForCausalLM as QMixFormer;
+use hf_hub::api::sync::Api;
+use hf_hub::Repo;
+use std::path::PathBuf;
+
+/// Causal language model the generation loop runs on
+pub trait TextModel: Send {
+  /// Forward `input` tokens at `position` and return last token logits
+  fn forward(
+    &mut self,
+    input: &Tensor,
+    position: usize,
+  ) -> candle_core::Result<Tensor>;
+
+  /// Forget all previously forwarded tokens
+  fn clear_kv_cache(&mut self);
+
+  /// Number of tokens in the model vocabulary
+  fn vocab_size(&self) -> usize;
+
+  /// Maximum number of tokens the model can attend to
+  fn context_length(&self) -> usize;
+}
+
+pub fn load(
+  config: &super::config::ModelConfig,
+  weights: &std::path::Path,
+  device: &Device,
+) -> anyhow::Result<Box<dyn TextModel>> {
+  Ok(match config.architecture {
+    super::config::ModelArchitecture::Phi2 => {
+      Box::new(Phi2::new(weights, device)?)
+    }
+    super::config::ModelArchitecture::Llama
+    | super::config::ModelArchitecture::Mistral => {
+      Box::new(Llama::new(weights, device)?)
+    }
+  })
+}
+
+struct Phi2 {
+  model: QMixFormer,
+}
+
+impl Phi2 {
+  // NOTE: the mixformer config fields are private so these mirror `v2`
+  const VOCAB_SIZE: usize = 51200;
+  const CONTEXT_LENGTH: usize = 2048;
+
+  fn new(weights: &std::path::Path, device: &Device) -> anyhow::Result<Self> {
+    let vb =
+      candle_transformers::quantized_var_builder::VarBuilder::from_gguf(
+        weights, device,
+      )?;
+    let model = QMixFormer::new_v2(&MixFormerConfig::v2(), vb)?;
+    Ok(Self { model })
+  }
+}
+
+impl TextModel for Phi2 {
+  fn forward(
+    &mut self,
+    input: &Tensor,
+    _position: usize,
+  ) -> candle_core::Result<Tensor> {
+    self.model.forward(input)
+  }
+
+  fn clear_kv_cache(&mut self) {
+    self.model.clear_kv_cache();
+  }
+
+  fn vocab_size(&self) -> usize {
+    Self::VOCAB_SIZE
+  }
+
+  fn context_length(&self) -> usize {
+    Self::CONTEXT_LENGTH
+  }
+}
+
+struct Llama {
+  model: QLlama,
+  vocab_size: usize,
+  context_length: usize,
+}
+
+impl Llama {
+  fn new(weights: &std::path::Path, device: &Device) -> anyhow::Result<Self> {
+    let mut file = std::fs::File::open(weights)?;
+    let content = gguf_file::Content::read(&mut file)?;
+
+    let vocab_size = content
+      .tensor_infos
+      .get("token_embd.weight")
+      .and_then(|info| info.shape.dims().first().copied())
+      .ok_or_else(|| anyhow::anyhow!("Model is missing token embeddings"))?;
+    let context_length = content
+      .metadata
+      .get("llama.context_length")
+      .ok_or_else(|| anyhow::anyhow!("Model is missing context length"))?
+      .to_u32()? as usize;
+
+    let model = QLlama::from_gguf(content, &mut file, device)?;
+    Ok(Self {
+      model,
+      vocab_size,
+      context_length,
+    })
+  }
+}
+
+impl TextModel for Llama {
+  fn forward(
+    &mut self,
+    input: &Tensor,
+    position: usize,
+  ) -> candle_core::Result<Tensor> {
+    self.model.forward(input, position)
+  }
+
+  fn clear_kv_cache(&mut self) {
+    // NOTE: the llama cache is overwritten when forwarding at position 0
+  }
+
+  fn vocab_size(&self) -> usize {
+    self.vocab_size
+  }
+
+  fn context_length(&self) -> usize {
+    self.context_length
+  }
+}
+
+pub(crate) struct ModelFiles {
+  pub(crate) weights: PathBuf,
+  pub(crate) tokenizer: PathBuf,
+}
+
+pub(crate) fn resolve(
+  config: &super::config::ModelConfig,
+) -> anyhow::Result<ModelFiles> {
+  Ok(ModelFiles {
+    weights: resolve_file(
+      config.weights.as_ref(),
+      config.download,
+      config.repo.as_str(),
+      config.repo_weights.as_str(),
+    )?,
+    tokenizer: resolve_file(
+      config.tokenizer.as_ref(),
+      config.download,
+      config.repo.as_str(),
+      config.repo_tokenizer.as_str(),
+    )?,
+  })
+}
+
+pub(crate) fn resolve_file(
+  local: Option<&PathBuf>,
+  download: bool,
+  repo: &str,
+  remote: &str,
+) -> anyhow::Result<PathBuf> {
+  if let Some(local) = local {
+    let local = local
+      .to_str()
+      .ok_or_else(|| anyhow::anyhow!("Invalid model file path {local:?}"))?;
+    let local = PathBuf::from(shellexpand::full(local)?.as_ref());
+    if std::fs::exists(&local).is_ok_and(|x| x) {
+      return Ok(local);
+    }
+    if !download {
+      return Err(anyhow::anyhow!("Model file {local:?} does not exist"));
+    }
+    tracing::warn!("Model file {local:?} does not exist so downloading");
+  }
+
+  if !download {
+    return Err(anyhow::anyhow!(
+      "No local path for {remote} and downloading is disabled"
+    ));
+  }
+
+  let api = Api::new()?;
+  let repo = api.repo(Repo::new(repo.to_string(), hf_hub::RepoType::Model));
+  Ok(repo.get(remote)?)
+}
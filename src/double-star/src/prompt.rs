@@ -0,0 +1,171 @@
+use tokenizers::Tokenizer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+  User,
+  Agent,
+}
+
+#[derive(Debug, Clone)]
+pub struct Turn {
+  pub role: Role,
+  pub content: String,
+}
+
+/// Render the conversation ending with the agent turn left open
+///
+/// The raw template leaves out the system prompt along with retrieved records.
+pub fn render(
+  template: super::config::PromptTemplate,
+  system: Option<&str>,
+  history: &[Turn],
+) -> String {
+  let mut prompt = String::new();
+
+  match template {
+    super::config::PromptTemplate::Raw => {
+      if let Some(turn) = history.last() {
+        prompt.push_str(&turn.content);
+      }
+    }
+    super::config::PromptTemplate::Phi => {
+      if let Some(system) = system {
+        prompt.push_str(system);
+        prompt.push('\n');
+      }
+      for turn in history {
+        match turn.role {
+          Role::User => prompt.push_str("Instruct: "),
+          Role::Agent => prompt.push_str("Output: "),
+        }
+        prompt.push_str(&turn.content);
+        prompt.push('\n');
+      }
+      prompt.push_str("Output:");
+    }
+    super::config::PromptTemplate::ChatMl => {
+      if let Some(system) = system {
+        prompt.push_str("<|im_start|>system\n");
+        prompt.push_str(system);
+        prompt.push_str("<|im_end|>\n");
+      }
+      for turn in history {
+        match turn.role {
+          Role::User => prompt.push_str("<|im_start|>user\n"),
+          Role::Agent => prompt.push_str("<|im_start|>assistant\n"),
+        }
+        prompt.push_str(&turn.content);
+        prompt.push_str("<|im_end|>\n");
+      }
+      prompt.push_str("<|im_start|>assistant\n");
+    }
+    super::config::PromptTemplate::Mistral => {
+      let mut system = system;
+      for turn in history {
+        match turn.role {
+          Role::User => {
+            prompt.push_str("[INST] ");
+            if let Some(system) = system.take() {
+              prompt.push_str(system);
+              prompt.push_str("\n\n");
+            }
+            prompt.push_str(&turn.content);
+            prompt.push_str(" [/INST]");
+          }
+          Role::Agent => {
+            prompt.push(' ');
+            prompt.push_str(&turn.content);
+            prompt.push_str("</s>");
+          }
+        }
+      }
+    }
+  }
+
+  prompt
+}
+
+/// Tokenize the conversation dropping the oldest turns to fit `budget`
+///
+/// The system prompt and template header are always kept and when the last
+/// turn alone doesn't fit its oldest text is cut instead.
+pub fn tokenize(
+  tokenizer: &Tokenizer,
+  template: super::config::PromptTemplate,
+  system: Option<&str>,
+  history: &[Turn],
+  budget: usize,
+) -> anyhow::Result<Vec<u32>> {
+  let mut start = 0usize;
+  loop {
+    let turns = history.get(start..).unwrap_or_default();
+    let tokens = encode(tokenizer, render(template, system, turns))?;
+
+    if tokens.len() <= budget {
+      return Ok(tokens);
+    }
+    if turns.len() <= 1 {
+      tracing::warn!("Prompt doesn't fit in context so truncating it");
+      return truncate(tokenizer, template, system, turns, budget);
+    }
+
+    // NOTE: the conversation should still start with a user turn
+    start = start.saturating_add(1);
+    while history.get(start..).is_some_and(|turns| {
+      turns.len() > 1
+        && turns.first().is_some_and(|turn| turn.role == Role::Agent)
+    }) {
+      start = start.saturating_add(1);
+    }
+  }
+}
+
+/// Cut the oldest text of the last turn until the prompt fits `budget`
+fn truncate(
+  tokenizer: &Tokenizer,
+  template: super::config::PromptTemplate,
+  system: Option<&str>,
+  turns: &[Turn],
+  budget: usize,
+) -> anyhow::Result<Vec<u32>> {
+  let Some(turn) = turns.last() else {
+    return Err(anyhow::anyhow!("Nothing to truncate"));
+  };
+  let content = match tokenizer.encode(turn.content.as_str(), false) {
+    Ok(result) => result.get_ids().to_vec(),
+    Err(_err) => return Err(anyhow::anyhow!("Tokenizer output bad")),
+  };
+
+  let mut keep = content.len();
+  loop {
+    let kept = content
+      .get(content.len().saturating_sub(keep)..)
+      .unwrap_or_default();
+    let kept = match tokenizer.decode(kept, false) {
+      Ok(kept) => kept,
+      Err(_err) => return Err(anyhow::anyhow!("Tokenizer output bad")),
+    };
+    let truncated = [Turn {
+      role: turn.role,
+      content: kept,
+    }];
+    let tokens = encode(tokenizer, render(template, system, &truncated))?;
+
+    if tokens.len() <= budget {
+      return Ok(tokens);
+    }
+    if keep == 0 {
+      tracing::warn!("System prompt doesn't fit in context");
+      return Ok(tokens);
+    }
+
+    keep = keep.saturating_sub(tokens.len().saturating_sub(budget).max(1));
+  }
+}
+
+fn encode(tokenizer: &Tokenizer, prompt: String) -> anyhow::Result<Vec<u32>> {
+  match tokenizer.encode(prompt, true) {
+    Ok(result) => Ok(result.get_ids().to_vec()),
+    Err(_err) => Err(anyhow::anyhow!("Tokenizer output bad")),
+  }
+}
@@ -0,0 +1,132 @@
+/// Find past messages and memos relevant to `query` outside of `chat`
+///
+/// Without an `embedding` only full text search is used.
+pub async fn retrieve(
+  db: &nebulon::client::Client,
+  config: &super::config::RetrievalConfig,
+  chat: &str,
+  query: &str,
+  embedding: Option<Vec<f32>>,
+) -> anyhow::Result<Vec<gravity::Citation>> {
+  let mut citations = Vec::<gravity::Citation>::new();
+  if config.limit == 0 {
+    return Ok(citations);
+  }
+
+  let mut push = |citation: gravity::Citation| {
+    let duplicate = citations.iter().any(|existing| {
+      existing.source == citation.source && existing.id == citation.id
+    });
+    if !duplicate {
+      citations.push(citation);
+    }
+  };
+
+  // NOTE: messages of the current chat are already in the prompt history
+  let candidates = config.limit.saturating_mul(2);
+  let messages = match (config.mode, embedding.clone()) {
+    (super::config::RetrievalMode::Off, _)
+    | (super::config::RetrievalMode::Semantic, None) => Vec::new(),
+    (super::config::RetrievalMode::Lexical, _)
+    | (super::config::RetrievalMode::Hybrid, None) => db
+      .search_messages(
+        nebulon::search::SearchQuery::new(query).limit(candidates),
+      )
+      .await?
+      .into_iter()
+      .map(|result| result.record)
+      .collect::<Vec<_>>(),
+    (super::config::RetrievalMode::Semantic, Some(embedding)) => db
+      .nearest_messages(embedding, candidates)
+      .await?
+      .into_iter()
+      .map(|result| result.record)
+      .collect::<Vec<_>>(),
+    (super::config::RetrievalMode::Hybrid, Some(embedding)) => db
+      .hybrid_search_messages(query, embedding, Default::default(), candidates)
+      .await?
+      .into_iter()
+      .map(|result| result.record)
+      .collect::<Vec<_>>(),
+  };
+  for message in messages {
+    if message.chat != chat {
+      push(gravity::Citation {
+        source: gravity::CitationSource::Message,
+        id: message.id,
+        content: message.content,
+      });
+    }
+  }
+
+  if config.memos && config.mode.semantic() {
+    if let Some(embedding) = embedding {
+      for result in db
+        .private_store()
+        .nearest_memos(embedding, config.limit)
+        .await?
+      {
+        push(gravity::Citation {
+          source: gravity::CitationSource::Memo,
+          id: result.record.id,
+          content: result.record.content,
+        });
+      }
+    }
+  }
+  if config.memos && config.mode.lexical() {
+    for result in db.private_store().search_memos(query).await? {
+      push(gravity::Citation {
+        source: gravity::CitationSource::Memo,
+        id: result.record.id,
+        content: result.record.content,
+      });
+    }
+  }
+
+  citations.truncate(config.limit);
+  Ok(citations)
+}
+
+/// Keep citations in order while their token `count` fits in `budget`
+pub fn fit(
+  citations: Vec<gravity::Citation>,
+  budget: usize,
+  count: impl Fn(&str) -> anyhow::Result<usize>,
+) -> anyhow::Result<Vec<gravity::Citation>> {
+  let mut used = 0usize;
+  let mut fitted = Vec::new();
+  for citation in citations {
+    let tokens = count(&citation.content)?;
+    if used.saturating_add(tokens) > budget {
+      break;
+    }
+    used = used.saturating_add(tokens);
+    fitted.push(citation);
+  }
+
+  Ok(fitted)
+}
+
+/// Append citations to the system prompt so templates render them as context
+pub fn system(
+  system: Option<&str>,
+  citations: &[gravity::Citation],
+) -> Option<String> {
+  if citations.is_empty() {
+    return system.map(ToOwned::to_owned);
+  }
+
+  let mut prompt = String::new();
+  if let Some(system) = system {
+    prompt.push_str(system);
+    prompt.push_str("\n\n");
+  }
+  prompt.push_str("Relevant context:");
+  for citation in citations {
+    prompt.push_str("\n- ");
+    prompt.push_str(&citation.content);
+  }
+
+  Some(prompt)
+}
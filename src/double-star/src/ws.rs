@@ -0,0 +1,181 @@
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::{
+  accept_hdr_async,
+  tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message,
+  },
+};
+
+type ConfigSubscribers = std::sync::Arc<
+  tokio::sync::Mutex<
+    Vec<flume::Sender<gravity::config::ConfigUpdate<super::config::Config>>>,
+  >,
+>;
+
+#[tokio::main]
+pub async fn run(
+  config: super::config::Config,
+  config_rx: flume::Receiver<
+    gravity::config::ConfigUpdate<super::config::Config>,
+  >,
+) -> anyhow::Result<()> {
+  let server_host = config.server.host.clone();
+  let server_port = config.server.port;
+
+  let listener =
+    tokio::net::TcpListener::bind(format!("{server_host}:{server_port}"))
+      .await?;
+  tracing::info!("Listening on ws://{server_host}:{server_port}/api/ws");
+
+  // NOTE: connections share the database so it is only migrated once
+  let db = super::connect(&config.db).await?;
+
+  let subscribers: ConfigSubscribers = Default::default();
+
+  let config = std::sync::Arc::new(tokio::sync::Mutex::new(config));
+  {
+    let config = config.clone();
+    let subscribers = subscribers.clone();
+    tokio::spawn(async move {
+      while let Ok(update) = config_rx.recv_async().await {
+        *config.lock().await = update.config.clone();
+        let mut subscribers = subscribers.lock().await;
+        subscribers.retain(|subscriber| {
+          subscriber
+            .send(gravity::config::ConfigUpdate {
+              config: update.config.clone(),
+              error: update.error.clone(),
+            })
+            .is_ok()
+        });
+      }
+    });
+  }
+
+  loop {
+    let (stream, address) = listener.accept().await?;
+    tracing::info!("Accepted connection from {address}");
+
+    let (config_tx, config_rx) = flume::unbounded();
+    subscribers.lock().await.push(config_tx);
+    let config = config.lock().await.clone();
+    let db = db.clone();
+
+    tokio::spawn(async move {
+      if let Err(err) = serve(stream, config, config_rx, db).await {
+        tracing::error!("Connection from {address} failed: {err}");
+      }
+      tracing::info!("Closed connection from {address}");
+    });
+  }
+}
+
+async fn serve(
+  stream: tokio::net::TcpStream,
+  config: super::config::Config,
+  config_rx: flume::Receiver<
+    gravity::config::ConfigUpdate<super::config::Config>,
+  >,
+  db: nebulon::client::Client,
+) -> anyhow::Result<()> {
+  let socket = accept_hdr_async(stream, accept_api_ws).await?;
+  let (mut socket_tx, mut socket_rx) = socket.split();
+
+  let (double_star_tx, double_star_rx) = flume::unbounded();
+  let (orbitus_tx, orbitus_rx) = flume::unbounded();
+
+  // NOTE: generation blocks so the agent gets its own thread
+  let runtime = tokio::runtime::Handle::current();
+  let double_star_handle = std::thread::spawn(move || {
+    if let Err(err) = runtime.block_on(super::agent(
+      double_star_tx,
+      orbitus_rx,
+      config,
+      config_rx,
+      db,
+    )) {
+      tracing::error!("Double Star error: {}", err);
+    }
+  });
+
+  let recv_handle = tokio::spawn(async move {
+    while let Some(Ok(message)) = socket_rx.next().await {
+      match message {
+        Message::Text(text) => {
+          match serde_json::de::from_str::<gravity::OrbitusMessage>(
+            text.as_str(),
+          ) {
+            Ok(message) => {
+              let should_break =
+                matches!(message, gravity::OrbitusMessage::Exited);
+              if let Err(err) = orbitus_tx.send_async(message).await {
+                tracing::error!("Failed forwarding message {}", err);
+              }
+              if should_break {
+                return;
+              }
+            }
+            Err(err) => {
+              tracing::warn!("Failed parsing message {}", err);
+            }
+          }
+        }
+        Message::Close(_) => {
+          break;
+        }
+        _ => {}
+      }
+    }
+
+    if let Err(err) =
+      orbitus_tx.send_async(gravity::OrbitusMessage::Exited).await
+    {
+      tracing::error!("Failed forwarding exit message {}", err);
+    }
+  });
+
+  let send_handle = tokio::spawn(async move {
+    while let Ok(message) = double_star_rx.recv_async().await {
+      if let Ok(message) = serde_json::ser::to_string(&message) {
+        if let Err(err) = socket_tx.send(Message::Text(message)).await {
+          tracing::error!("Failed forwarding message {}", err);
+        }
+      }
+    }
+    if let Err(err) = socket_tx.close().await {
+      tracing::error!("Failed closing websocket {}", err);
+    }
+  });
+
+  if let (_, Err(err)) | (Err(err), _) = tokio::join!(send_handle, recv_handle)
+  {
+    tracing::error!("Failed closing websocket {}", err)
+  };
+
+  if let Err(err) =
+    tokio::task::spawn_blocking(move || double_star_handle.join()).await?
+  {
+    return Err(anyhow::anyhow!("Failed joining double star {:?}", err));
+  }
+
+  Ok(())
+}
+
+#[allow(
+  clippy::result_large_err,
+  reason = "signature is dictated by tungstenite"
+)]
+fn accept_api_ws(
+  request: &Request,
+  response: Response,
+) -> Result<Response, ErrorResponse> {
+  if request.uri().path() == "/api/ws" {
+    return Ok(response);
+  }
+
+  let mut response = ErrorResponse::new(Some("Not found".to_string()));
+  *response.status_mut() = StatusCode::NOT_FOUND;
+  Err(response)
+}
@@ -0,0 +1,27 @@
+pub struct Setup {
+  pub model: Box<dyn double_star::model::TextModel>,
+  pub tokenizer: tokenizers::Tokenizer,
+  pub device: candle_core::Device,
+}
+
+pub fn setup() -> anyhow::Result<Setup> {
+  let weights = std::path::PathBuf::from(std::env::var(
+    "DOUBLE_STAR_TEST_MODEL_WEIGHTS",
+  )?);
+  let tokenizer = std::env::var("DOUBLE_STAR_TEST_MODEL_TOKENIZER")?;
+
+  let device = candle_core::Device::Cpu;
+  let model = double_star::model::load(
+    &double_star::config::ModelConfig::default(),
+    &weights,
+    &device,
+  )?;
+  let tokenizer = tokenizers::Tokenizer::from_file(tokenizer)
+    .map_err(|err| anyhow::anyhow!(err))?;
+
+  Ok(Setup {
+    model,
+    tokenizer,
+    device,
+  })
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use candle_core::{DType, Tensor};
+use candle_transformers::generation::LogitsProcessor;
+
+#[test]
+#[ignore = "needs DOUBLE_STAR_TEST_MODEL_{WEIGHTS,TOKENIZER} model files"]
+fn test_incremental_decoding_matches_full() -> anyhow::Result<()> {
+  let mut setup = common::setup()?;
+
+  let steps = 16;
+  let prompt = setup
+    .tokenizer
+    .encode("The capital of France is", true)
+    .map_err(|err| anyhow::anyhow!(err))?
+    .get_ids()
+    .to_vec();
+
+  let mut logits_processor = LogitsProcessor::new(0, None, None);
+  let mut full = prompt.clone();
+  let full_start = std::time::Instant::now();
+  for _ in 0..steps {
+    let input = Tensor::new(full.as_slice(), &setup.device)?.unsqueeze(0)?;
+    setup.model.clear_kv_cache();
+    let logits = setup
+      .model
+      .forward(&input, 0)?
+      .to_dtype(DType::F32)?
+      .squeeze(0)?;
+    full.push(logits_processor.sample(&logits)?);
+  }
+  let full_elapsed = full_start.elapsed();
+
+  let mut logits_processor = LogitsProcessor::new(0, None, None);
+  let mut incremental = prompt.clone();
+  let incremental_start = std::time::Instant::now();
+  {
+    let mut decoder = double_star::generation::Decoder::new(
+      setup.model.as_mut(),
+      &setup.device,
+    );
+    let mut input = prompt.clone();
+    for _ in 0..steps {
+      let logits = decoder.forward(&input)?;
+      let next_token = logits_processor.sample(&logits)?;
+      incremental.push(next_token);
+      input = vec![next_token];
+    }
+  }
+  let incremental_elapsed = incremental_start.elapsed();
+
+  // NOTE: tests have no tracing subscriber and the timings are only shown
+  // when running with --nocapture
+  eprintln!(
+    "full took {full_elapsed:?} and incremental {incremental_elapsed:?}"
+  );
+
+  assert_eq!(
+    full.get(prompt.len()..),
+    incremental.get(prompt.len()..),
+    "decoding with the KV cache diverged from decoding the full sequence"
+  );
+
+  Ok(())
+}
@@ -0,0 +1,140 @@
+use double_star::config::PromptTemplate;
+use double_star::prompt::{render, tokenize, Role, Turn};
+
+fn history() -> Vec<Turn> {
+  vec![
+    Turn {
+      role: Role::User,
+      content: "Hi".to_string(),
+    },
+    Turn {
+      role: Role::Agent,
+      content: "Hello".to_string(),
+    },
+    Turn {
+      role: Role::User,
+      content: "How are you?".to_string(),
+    },
+  ]
+}
+
+#[test]
+fn test_render_phi() {
+  assert_eq!(
+    render(PromptTemplate::Phi, Some("Be nice."), &history()),
+    "Be nice.\nInstruct: Hi\nOutput: Hello\nInstruct: How are you?\nOutput:"
+  );
+}
+
+#[test]
+fn test_render_chatml() {
+  assert_eq!(
+    render(PromptTemplate::ChatMl, None, &history()),
+    concat!(
+      "<|im_start|>user\nHi<|im_end|>\n",
+      "<|im_start|>assistant\nHello<|im_end|>\n",
+      "<|im_start|>user\nHow are you?<|im_end|>\n",
+      "<|im_start|>assistant\n",
+    )
+  );
+}
+
+#[test]
+fn test_render_mistral() {
+  assert_eq!(
+    render(PromptTemplate::Mistral, Some("Be nice."), &history()),
+    "[INST] Be nice.\n\nHi [/INST] Hello</s>[INST] How are you? [/INST]"
+  );
+}
+
+#[test]
+fn test_render_raw() {
+  assert_eq!(
+    render(PromptTemplate::Raw, Some("Be nice."), &history()),
+    "How are you?"
+  );
+}
+
+#[test]
+fn test_render_raw_without_records() {
+  let system = double_star::retrieval::system(
+    Some("Be nice."),
+    &[gravity::Citation {
+      source: gravity::CitationSource::Memo,
+      id: "memo".to_string(),
+      content: "Likes tea".to_string(),
+    }],
+  );
+
+  assert_eq!(
+    render(PromptTemplate::Raw, system.as_deref(), &history()),
+    "How are you?"
+  );
+  assert!(!PromptTemplate::Raw.renders_system());
+  assert!(PromptTemplate::Phi.renders_system());
+}
+
+fn tokenizer() -> anyhow::Result<tokenizers::Tokenizer> {
+  let vocab = [
+    "[UNK]", "Be", "nice.", "Instruct:", "Output:", "Hi", "Hello", "How",
+    "are", "you?", "one", "two", "three", "four", "five", "six",
+  ]
+  .into_iter()
+  .zip(0u32..)
+  .map(|(word, id)| (word.to_string(), id))
+  .collect();
+  let model = tokenizers::models::wordlevel::WordLevel::builder()
+    .vocab(vocab)
+    .unk_token("[UNK]".to_string())
+    .build()
+    .map_err(|err| anyhow::anyhow!(err))?;
+  let mut tokenizer = tokenizers::Tokenizer::new(model);
+  tokenizer.with_pre_tokenizer(Some(
+    tokenizers::pre_tokenizers::whitespace::WhitespaceSplit,
+  ));
+
+  Ok(tokenizer)
+}
+
+fn decode(
+  tokenizer: &tokenizers::Tokenizer,
+  tokens: &[u32],
+) -> anyhow::Result<String> {
+  tokenizer
+    .decode(tokens, false)
+    .map_err(|err| anyhow::anyhow!(err))
+}
+
+#[test]
+fn test_tokenize_drops_oldest_turns() -> anyhow::Result<()> {
+  let tokenizer = tokenizer()?;
+
+  let tokens =
+    tokenize(&tokenizer, PromptTemplate::Phi, Some("Be nice."), &history(), 8)?;
+
+  assert_eq!(
+    decode(&tokenizer, &tokens)?,
+    "Be nice. Instruct: How are you? Output:"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn test_tokenize_keeps_header_when_truncating() -> anyhow::Result<()> {
+  let tokenizer = tokenizer()?;
+  let history = vec![Turn {
+    role: Role::User,
+    content: "one two three four five six".to_string(),
+  }];
+
+  let tokens =
+    tokenize(&tokenizer, PromptTemplate::Phi, Some("Be nice."), &history, 7)?;
+
+  assert_eq!(
+    decode(&tokenizer, &tokens)?,
+    "Be nice. Instruct: four five six Output:"
+  );
+
+  Ok(())
+}
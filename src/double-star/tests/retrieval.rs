@@ -0,0 +1,97 @@
+use double_star::retrieval::{fit, retrieve, system};
+use gravity::{Citation, CitationSource};
+
+fn citations() -> Vec<Citation> {
+  vec![
+    Citation {
+      source: CitationSource::Message,
+      id: "a".to_string(),
+      content: "one two three".to_string(),
+    },
+    Citation {
+      source: CitationSource::Memo,
+      id: "b".to_string(),
+      content: "four five".to_string(),
+    },
+    Citation {
+      source: CitationSource::Message,
+      id: "c".to_string(),
+      content: "six".to_string(),
+    },
+  ]
+}
+
+fn count(content: &str) -> anyhow::Result<usize> {
+  Ok(content.split_whitespace().count())
+}
+
+#[test]
+fn test_fit_keeps_citations_within_budget() -> anyhow::Result<()> {
+  let fitted = fit(citations(), 5, count)?
+    .into_iter()
+    .map(|citation| citation.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(fitted, vec!["a".to_string(), "b".to_string()]);
+
+  Ok(())
+}
+
+#[test]
+fn test_fit_stops_at_first_citation_over_budget() -> anyhow::Result<()> {
+  let fitted = fit(citations(), 2, count)?;
+
+  assert!(fitted.is_empty());
+
+  Ok(())
+}
+
+#[test]
+fn test_system_with_citations() {
+  let citations = citations();
+
+  assert_eq!(
+    system(Some("Be nice."), citations.get(..2).unwrap_or_default()),
+    Some(
+      "Be nice.\n\nRelevant context:\n- one two three\n- four five"
+        .to_string()
+    )
+  );
+}
+
+#[test]
+fn test_system_without_citations() {
+  assert_eq!(system(Some("Be nice."), &[]), Some("Be nice.".to_string()));
+  assert_eq!(system(None, &[]), None);
+}
+
+#[tokio::test]
+async fn test_retrieve_without_embedding() -> anyhow::Result<()> {
+  let db = nebulon::client::connect(nebulon::config::ClientConfig {
+    connection: nebulon::config::ConnectionConfig::Memory,
+    ..Default::default()
+  })
+  .await?;
+  db.migrate().await?;
+  let chat = db.insert_chat().await?;
+  let other_chat = db.insert_chat().await?;
+  let message = db
+    .insert_message(other_chat.id, "user".to_string(), "rust".to_string())
+    .await?;
+
+  let retrieved = retrieve(
+    &db,
+    &double_star::config::RetrievalConfig::default(),
+    &chat.id,
+    "rust",
+    None,
+  )
+  .await?
+  .into_iter()
+  .map(|citation| citation.id)
+  .collect::<Vec<_>>();
+
+  assert_eq!(retrieved, vec![message.id]);
+
+  Ok(())
+}
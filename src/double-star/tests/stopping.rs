@@ -0,0 +1,54 @@
+#[test]
+fn test_stop_sequence_is_held_back() {
+  let mut stopper =
+    double_star::generation::Stopper::new(vec!["\nUser:".to_string()]);
+
+  assert_eq!(stopper.push("Hello"), ("Hello".to_string(), None));
+  assert_eq!(stopper.push(" there\n"), (" there".to_string(), None));
+  assert_eq!(stopper.push("Us"), ("".to_string(), None));
+  assert_eq!(
+    stopper.push("er: hi"),
+    ("".to_string(), Some("\nUser:".to_string()))
+  );
+  assert_eq!(stopper.finish(), "");
+}
+
+#[test]
+fn test_held_back_text_is_released() {
+  let mut stopper =
+    double_star::generation::Stopper::new(vec!["\nUser:".to_string()]);
+
+  assert_eq!(stopper.push("one\n"), ("one".to_string(), None));
+  assert_eq!(stopper.push("two"), ("\ntwo".to_string(), None));
+  assert_eq!(stopper.push("\nUs"), ("".to_string(), None));
+  assert_eq!(stopper.finish(), "\nUs");
+}
+
+#[test]
+fn test_special_stop_token_is_matched() -> anyhow::Result<()> {
+  let vocab = [("[UNK]", 0), ("Hello", 1)]
+    .into_iter()
+    .map(|(word, id)| (word.to_string(), id))
+    .collect();
+  let model = tokenizers::models::wordlevel::WordLevel::builder()
+    .vocab(vocab)
+    .unk_token("[UNK]".to_string())
+    .build()
+    .map_err(|err| anyhow::anyhow!(err))?;
+  let mut tokenizer = tokenizers::Tokenizer::new(model);
+  tokenizer.add_special_tokens(&[tokenizers::AddedToken::from(
+    "<|im_end|>",
+    true,
+  )]);
+  let stop = vec!["<|im_end|>".to_string(), "Hello".to_string()];
+
+  let stop_tokens =
+    double_star::generation::special_stop_tokens(&tokenizer, &stop);
+  let mut detokenizer = double_star::generation::Detokenizer::new(&tokenizer);
+
+  assert_eq!(stop_tokens.len(), 1);
+  assert_eq!(stop_tokens.get(&2), Some(&"<|im_end|>".to_string()));
+  assert_eq!(detokenizer.push(2)?, "");
+
+  Ok(())
+}
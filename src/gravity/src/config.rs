@@ -29,7 +29,7 @@ pub async fn new_async<T: Values + 'static>(
   organization: &str,
   application: &str,
   root: &str,
-) -> Config<T> {
+) -> anyhow::Result<Config<T>> {
   Config::new_async(prefix, qualifier, organization, application, root).await
 }
 
@@ -39,10 +39,44 @@ pub fn new<T: Values + 'static>(
   organization: &str,
   application: &str,
   root: &str,
-) -> Config<T> {
+) -> anyhow::Result<Config<T>> {
   Config::new(prefix, qualifier, organization, application, root)
 }
 
+/// Parse env config from variables named `{prefix}_{FIELD}`
+pub fn from_env<T: serde::de::DeserializeOwned>(
+  prefix: &str,
+) -> anyhow::Result<T> {
+  envy::prefixed(format!("{prefix}_"))
+    .from_env()
+    .map_err(|err| anyhow::format_err!("Invalid {prefix} env config: {err}"))
+}
+
+/// Deserialize a value from a string or the value itself
+///
+/// Env values of flattened fields arrive as strings so fields that aren't
+/// strings have to be parsed with this.
+pub fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+  D: serde::Deserializer<'de>,
+  T: std::str::FromStr + serde::Deserialize<'de>,
+  T::Err: std::fmt::Display,
+{
+  #[derive(serde::Deserialize)]
+  #[serde(untagged)]
+  enum StringOrValue<T> {
+    String(String),
+    Value(T),
+  }
+
+  match <StringOrValue<T> as serde::Deserialize>::deserialize(deserializer)? {
+    StringOrValue::String(string) => {
+      string.parse().map_err(serde::de::Error::custom)
+    }
+    StringOrValue::Value(value) => Ok(value),
+  }
+}
+
 pub struct ConfigUpdate<T: Values + 'static> {
   pub config: T,
   pub error: Option<std::sync::Arc<anyhow::Error>>,
@@ -237,7 +271,7 @@ impl<T: Values> Config<T> {
     organization: &str,
     application: &str,
     root: &str,
-  ) -> Self {
+  ) -> anyhow::Result<Self> {
     let prefix = prefix.to_string();
     let qualifier = qualifier.to_string();
     let organization = organization.to_string();
@@ -254,7 +288,7 @@ impl<T: Values> Config<T> {
     let _ = dotenvy::dotenv();
 
     let raw_values =
-      Self::load(&prefix, &qualifier, &organization, &application);
+      Self::load(&prefix, &qualifier, &organization, &application)?;
 
     if raw_values.print_schema {
       Self::print_schema()
@@ -294,7 +328,7 @@ impl<T: Values> Config<T> {
       watch_paths,
     );
 
-    Self {
+    Ok(Self {
       values,
       watcher,
       tx,
@@ -303,7 +337,7 @@ impl<T: Values> Config<T> {
       qualifier,
       organization,
       application,
-    }
+    })
   }
 
   async fn new_async(
@@ -312,7 +346,7 @@ impl<T: Values> Config<T> {
     organization: &str,
     application: &str,
     root: &str,
-  ) -> Self {
+  ) -> anyhow::Result<Self> {
     let prefix = prefix.to_string();
     let qualifier = qualifier.to_string();
     let organization = organization.to_string();
@@ -327,7 +361,8 @@ impl<T: Values> Config<T> {
     };
 
     let raw_values =
-      Self::load_async(&prefix, &qualifier, &organization, &application).await;
+      Self::load_async(&prefix, &qualifier, &organization, &application)
+        .await?;
 
     if raw_values.print_schema {
       Self::print_schema()
@@ -368,7 +403,7 @@ impl<T: Values> Config<T> {
       watch_paths,
     );
 
-    Self {
+    Ok(Self {
       values,
       watcher,
       tx,
@@ -377,7 +412,7 @@ impl<T: Values> Config<T> {
       qualifier,
       organization,
       application,
-    }
+    })
   }
 
   fn load(
@@ -385,12 +420,9 @@ impl<T: Values> Config<T> {
     qualifier: &str,
     organization: &str,
     application: &str,
-  ) -> Wrapper<T> {
+  ) -> anyhow::Result<Wrapper<T>> {
     let args = Self::parse_args();
-    let env = Self::parse_env(prefix).unwrap_or_else(|err| {
-      tracing::debug!("Using default env config: {}", err);
-      Default::default()
-    });
+    let env = Self::parse_env(prefix)?;
     let mut values = T::new(args.values, env.values);
 
     let mut file = None;
@@ -412,21 +444,21 @@ impl<T: Values> Config<T> {
 
     if let Some(file) = file {
       values.import(file.values);
-      Wrapper {
+      Ok(Wrapper {
         values,
         config_path: args.config_path.and(env.config_path),
         log_level: args.log_level.and(env.log_level).and(file.log_level),
         print_schema: args.print_schema,
         print_config: args.print_config,
-      }
+      })
     } else {
-      Wrapper {
+      Ok(Wrapper {
         values,
         config_path: args.config_path.and(env.config_path),
         log_level: args.log_level.and(env.log_level),
         print_schema: args.print_schema,
         print_config: args.print_config,
-      }
+      })
     }
   }
 
@@ -435,12 +467,9 @@ impl<T: Values> Config<T> {
     qualifier: &str,
     organization: &str,
     application: &str,
-  ) -> Wrapper<T> {
+  ) -> anyhow::Result<Wrapper<T>> {
     let args = Self::parse_args_async().await;
-    let env = Self::parse_env_async(prefix).await.unwrap_or_else(|err| {
-      tracing::debug!("Using default env config: {}", err);
-      Default::default()
-    });
+    let env = Self::parse_env_async(prefix).await?;
     let mut values = T::new(args.values, env.values);
 
     let mut file = None;
@@ -466,21 +495,21 @@ impl<T: Values> Config<T> {
 
     if let Some(file) = file {
       values.import(file.values);
-      Wrapper {
+      Ok(Wrapper {
         values,
         config_path: args.config_path.and(env.config_path),
         log_level: args.log_level.and(env.log_level).and(file.log_level),
         print_schema: args.print_schema,
         print_config: args.print_config,
-      }
+      })
     } else {
-      Wrapper {
+      Ok(Wrapper {
         values,
         config_path: args.config_path.and(env.config_path),
         log_level: args.log_level.and(env.log_level),
         print_schema: args.print_schema,
         print_config: args.print_config,
-      }
+      })
     }
   }
 
@@ -625,17 +654,13 @@ impl<T: Values> Config<T> {
   }
 
   fn parse_env(prefix: &str) -> anyhow::Result<EnvWrapper<T::TEnv>> {
-    envy::prefixed(prefix)
-      .from_env()
-      .map_err(|err| anyhow::format_err!(err))
+    from_env(prefix)
   }
 
   async fn parse_env_async(
     prefix: &str,
   ) -> anyhow::Result<EnvWrapper<T::TEnv>> {
-    envy::prefixed(prefix)
-      .from_env()
-      .map_err(|err| anyhow::format_err!(err))
+    from_env(prefix)
   }
 
   fn parse_file(
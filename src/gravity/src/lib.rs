@@ -13,12 +13,53 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DoubleStarMessage {
+  Retrieved(Vec<Citation>),
   Generated(String),
-  Break,
+  Break(StopReason),
+  Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StopReason {
+  Eos,
+  MaxNewTokens,
+  ContextLength,
+  StopSequence(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+  pub source: CitationSource,
+  pub id: String,
+  pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CitationSource {
+  Message,
+  Memo,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OrbitusMessage {
-  Submit(String),
+  Submit(Prompt),
+  Cancel,
   Exited,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prompt {
+  pub content: String,
+  #[serde(default)]
+  pub sampling: SamplingOverride,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SamplingOverride {
+  pub temperature: Option<f64>,
+  pub top_p: Option<f64>,
+  pub top_k: Option<usize>,
+  pub seed: Option<u64>,
+  pub repeat_penalty: Option<f32>,
+  pub repeat_last_n: Option<usize>,
+}
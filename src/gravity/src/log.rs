@@ -9,7 +9,8 @@ pub fn init(prefix: &str) -> anyhow::Result<Handle<EnvFilter, Registry>> {
   #[cfg(debug_assertions)]
   let level = "debug";
 
-  let format_layer = tracing_subscriber::fmt::layer();
+  // NOTE: stdout is left for command output like archives
+  let format_layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
   let (filter_layer, filter_handle) = tracing_subscriber::reload::Layer::new(
     build_tracing_filter(prefix, level)?,
   );
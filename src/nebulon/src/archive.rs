@@ -0,0 +1,674 @@
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use surrealdb::{
+  engine::any::Any,
+  sql::{Bytes, Datetime, Thing},
+  RecordId, Surreal,
+};
+
+use super::connection::WithTimeout;
+use super::migration::MigrationStore;
+
+/// Archive format version written by this binary
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Number of records exported or imported per kind
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveSummary {
+  pub chats: usize,
+  pub messages: usize,
+  pub files: usize,
+  pub attachments: usize,
+  pub memos: usize,
+  pub journal_entries: usize,
+  /// Records left out because they have no chat or because private records
+  /// weren't requested
+  pub skipped: usize,
+}
+
+impl std::fmt::Display for ArchiveSummary {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} chats, {} messages, {} files, {} attachments, {} memos \
+        and {} journal entries",
+      self.chats,
+      self.messages,
+      self.files,
+      self.attachments,
+      self.memos,
+      self.journal_entries
+    )?;
+    if self.skipped > 0 {
+      write!(f, " (skipped {} records)", self.skipped)?;
+    }
+    Ok(())
+  }
+}
+
+impl ArchiveSummary {
+  fn count(&mut self, record: &Record) {
+    let count = match record {
+      Record::Header { .. } => return,
+      Record::Chat { .. } => &mut self.chats,
+      Record::Message { .. } => &mut self.messages,
+      Record::File { .. } => &mut self.files,
+      Record::Attachment { .. } => &mut self.attachments,
+      Record::Memo { .. } => &mut self.memos,
+      Record::JournalEntry { .. } => &mut self.journal_entries,
+    };
+    *count = count.saturating_add(1);
+  }
+}
+
+/// One line of a JSON Lines archive
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Record {
+  Header {
+    version: u32,
+    private: bool,
+  },
+  Chat {
+    id: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    last_interaction: Option<chrono::DateTime<chrono::Utc>>,
+  },
+  Message {
+    id: String,
+    chat: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    sender: String,
+    content: String,
+    embedding: Option<Vec<f32>>,
+  },
+  File {
+    store: MigrationStore,
+    id: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    title: String,
+    extension: String,
+    description: String,
+    /// Base64 encoded file contents
+    data: String,
+    embedding: Option<Vec<f32>>,
+  },
+  /// File attached to a message or to a memo in the private store
+  Attachment {
+    store: MigrationStore,
+    file: String,
+    target: String,
+  },
+  Memo {
+    id: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    content: String,
+    embedding: Option<Vec<f32>>,
+  },
+  JournalEntry {
+    id: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    content: String,
+  },
+}
+
+impl Record {
+  fn store(&self) -> MigrationStore {
+    match self {
+      Self::Header { .. } | Self::Chat { .. } | Self::Message { .. } => {
+        MigrationStore::Public
+      }
+      Self::File { store, .. } | Self::Attachment { store, .. } => *store,
+      Self::Memo { .. } | Self::JournalEntry { .. } => MigrationStore::Private,
+    }
+  }
+
+  /// Records are restored in this order so relations are created last
+  fn rank(&self) -> u8 {
+    match self {
+      Self::Header { .. } => 0,
+      Self::Chat { .. } => 1,
+      Self::Message { .. } => 2,
+      Self::Memo { .. } => 3,
+      Self::JournalEntry { .. } => 4,
+      Self::File { .. } => 5,
+      Self::Attachment { .. } => 6,
+    }
+  }
+}
+
+pub(crate) async fn export(
+  public: &Surreal<Any>,
+  private: Option<&Surreal<Any>>,
+  timeout: Duration,
+  mut writer: impl Write,
+) -> super::Result<ArchiveSummary> {
+  let mut summary = ArchiveSummary::default();
+  let mut records = vec![Record::Header {
+    version: ARCHIVE_VERSION,
+    private: private.is_some(),
+  }];
+  records.extend(chats(public, timeout).await?);
+  records.extend(messages(public, timeout, &mut summary).await?);
+  records.extend(files(public, MigrationStore::Public, timeout).await?);
+  records.extend(attachments(public, MigrationStore::Public, timeout).await?);
+  if let Some(private) = private {
+    records.extend(memos(private, timeout).await?);
+    records.extend(journal_entries(private, timeout).await?);
+    records.extend(files(private, MigrationStore::Private, timeout).await?);
+    records.extend(
+      attachments(private, MigrationStore::Private, timeout).await?,
+    );
+  }
+
+  for record in records {
+    summary.count(&record);
+    serde_json::to_writer(&mut writer, &record).map_err(write_error)?;
+    writeln!(writer).map_err(write_error)?;
+  }
+  writer.flush().map_err(write_error)?;
+
+  Ok(summary)
+}
+
+pub(crate) async fn import(
+  public: &Surreal<Any>,
+  private: Option<&Surreal<Any>>,
+  timeout: Duration,
+  embedding_dimension: usize,
+  reader: impl BufRead,
+) -> super::Result<ArchiveSummary> {
+  let mut records = read(reader)?;
+  records.sort_by_key(Record::rank);
+
+  // NOTE: relating messages to chats updates their last interaction
+  let interactions = records
+    .iter()
+    .filter_map(|record| match record {
+      Record::Chat {
+        id,
+        last_interaction,
+        ..
+      } => Some((id.clone(), *last_interaction)),
+      _ => None,
+    })
+    .collect::<Vec<_>>();
+
+  let mut summary = ArchiveSummary::default();
+  for record in records {
+    let db = match record.store() {
+      MigrationStore::Public => Some(public),
+      MigrationStore::Private => private,
+    };
+    let Some(db) = db else {
+      summary.skipped = summary.skipped.saturating_add(1);
+      continue;
+    };
+    summary.count(&record);
+    restore(db, record, timeout, embedding_dimension).await?;
+  }
+
+  if summary.skipped > 0 {
+    tracing::warn!(
+      "Skipped {} private records because private import wasn't requested",
+      summary.skipped
+    );
+  }
+
+  for (chat, last_interaction) in interactions {
+    public
+      .query("UPDATE $chat SET last_interaction = $last_interaction;")
+      .bind(("chat", RecordId::from(("chat", chat))))
+      .bind(("last_interaction", last_interaction.map(Datetime::from)))
+      .with_timeout(timeout)
+      .await?
+      .check()?;
+  }
+
+  Ok(summary)
+}
+
+fn read(reader: impl BufRead) -> super::Result<Vec<Record>> {
+  let mut records = Vec::new();
+  for (index, line) in reader.lines().enumerate() {
+    let line = line.map_err(|err| {
+      super::Error::Archive(format!("Reading archive failed: {err}"))
+    })?;
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let record = serde_json::from_str::<Record>(&line).map_err(|err| {
+      let line = index.saturating_add(1);
+      super::Error::Archive(format!("Invalid record on line {line}: {err}"))
+    })?;
+    records.push(record);
+  }
+
+  match records.first() {
+    Some(Record::Header { version, .. }) if *version <= ARCHIVE_VERSION => {
+      Ok(records)
+    }
+    Some(Record::Header { version, .. }) => {
+      Err(super::Error::Archive(format!(
+        "Archive version {version} is newer than \
+          supported version {ARCHIVE_VERSION}"
+      )))
+    }
+    _ => Err(super::Error::Archive(
+      "Archive doesn't start with a header".to_string(),
+    )),
+  }
+}
+
+/// Create or overwrite a record so importing twice changes nothing
+async fn restore(
+  db: &Surreal<Any>,
+  record: Record,
+  timeout: Duration,
+  embedding_dimension: usize,
+) -> super::Result<()> {
+  // NOTE: embeddings of another dimension have to be recomputed
+  let embedding = |embedding: Option<Vec<f32>>| {
+    embedding.filter(|embedding| embedding.len() == embedding_dimension)
+  };
+
+  match record {
+    Record::Header { .. } => {}
+    Record::Chat {
+      id,
+      timestamp,
+      last_interaction,
+    } => {
+      #[derive(serde::Serialize)]
+      struct InChat {
+        timestamp: Datetime,
+        last_interaction: Option<Datetime>,
+      }
+
+      db.query("UPSERT $chat CONTENT $content;")
+        .bind(("chat", RecordId::from(("chat", id))))
+        .bind((
+          "content",
+          InChat {
+            timestamp: Datetime::from(timestamp),
+            last_interaction: last_interaction.map(Datetime::from),
+          },
+        ))
+        .with_timeout(timeout)
+        .await?
+        .check()?;
+    }
+    Record::Message {
+      id,
+      chat,
+      timestamp,
+      sender,
+      content,
+      embedding: message_embedding,
+    } => {
+      #[derive(serde::Serialize)]
+      struct InMessage {
+        timestamp: Datetime,
+        sender: String,
+        content: String,
+        embedding: Option<Vec<f32>>,
+      }
+
+      let query = r#"
+        BEGIN;
+        UPSERT $message CONTENT $content;
+        IF array::len(
+          (SELECT id FROM posted_in WHERE in = $message AND out = $chat)
+        ) = 0 {
+          RELATE $message->posted_in->$chat;
+        };
+        COMMIT;
+      "#;
+
+      db.query(query)
+        .bind(("message", RecordId::from(("message", id))))
+        .bind(("chat", RecordId::from(("chat", chat))))
+        .bind((
+          "content",
+          InMessage {
+            timestamp: Datetime::from(timestamp),
+            sender,
+            content,
+            embedding: embedding(message_embedding),
+          },
+        ))
+        .with_timeout(timeout)
+        .await?
+        .check()?;
+    }
+    Record::File {
+      store: _,
+      id,
+      timestamp,
+      title,
+      extension,
+      description,
+      data,
+      embedding: file_embedding,
+    } => {
+      let data = BASE64_STANDARD.decode(data).map_err(|err| {
+        super::Error::Archive(format!("Invalid data of file {id}: {err}"))
+      })?;
+
+      let query = r#"
+        UPSERT $file SET
+          timestamp = $timestamp,
+          data = $data,
+          title = $title,
+          extension = $extension,
+          description = $description,
+          embedding = $embedding;
+      "#;
+
+      db.query(query)
+        .bind(("file", RecordId::from(("file", id))))
+        .bind(("timestamp", Datetime::from(timestamp)))
+        .bind(("data", super::client::bytes(data)))
+        .bind(("title", title))
+        .bind(("extension", extension))
+        .bind(("description", description))
+        .bind(("embedding", embedding(file_embedding)))
+        .with_timeout(timeout)
+        .await?
+        .check()?;
+    }
+    Record::Attachment {
+      store,
+      file,
+      target,
+    } => {
+      let table = match store {
+        MigrationStore::Public => "message",
+        MigrationStore::Private => "memo",
+      };
+
+      let query = r#"
+        IF array::len(
+          (SELECT id FROM attached_to WHERE in = $file AND out = $target)
+        ) = 0 {
+          RELATE $file->attached_to->$target;
+        };
+      "#;
+
+      db.query(query)
+        .bind(("file", RecordId::from(("file", file))))
+        .bind(("target", RecordId::from((table, target))))
+        .with_timeout(timeout)
+        .await?
+        .check()?;
+    }
+    Record::Memo {
+      id,
+      timestamp,
+      content,
+      embedding: memo_embedding,
+    } => {
+      #[derive(serde::Serialize)]
+      struct InMemo {
+        timestamp: Datetime,
+        content: String,
+        embedding: Option<Vec<f32>>,
+      }
+
+      db.query("UPSERT $memo CONTENT $content;")
+        .bind(("memo", RecordId::from(("memo", id))))
+        .bind((
+          "content",
+          InMemo {
+            timestamp: Datetime::from(timestamp),
+            content,
+            embedding: embedding(memo_embedding),
+          },
+        ))
+        .with_timeout(timeout)
+        .await?
+        .check()?;
+    }
+    Record::JournalEntry {
+      id,
+      timestamp,
+      content,
+    } => {
+      #[derive(serde::Serialize)]
+      struct InJournalEntry {
+        timestamp: Datetime,
+        content: String,
+      }
+
+      db.query("UPSERT $entry CONTENT $content;")
+        .bind(("entry", RecordId::from(("journal", id))))
+        .bind((
+          "content",
+          InJournalEntry {
+            timestamp: Datetime::from(timestamp),
+            content,
+          },
+        ))
+        .with_timeout(timeout)
+        .await?
+        .check()?;
+    }
+  }
+
+  Ok(())
+}
+
+async fn chats(
+  db: &Surreal<Any>,
+  timeout: Duration,
+) -> super::Result<Vec<Record>> {
+  #[derive(serde::Deserialize)]
+  struct OutChat {
+    id: Thing,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    last_interaction: Option<chrono::DateTime<chrono::Utc>>,
+  }
+
+  let chats = db
+    .query("SELECT * FROM chat ORDER BY timestamp, id;")
+    .with_timeout(timeout)
+    .await?
+    .take::<Vec<OutChat>>(0)?;
+
+  Ok(
+    chats
+      .into_iter()
+      .map(|chat| Record::Chat {
+        id: chat.id.id.to_raw(),
+        timestamp: chat.timestamp,
+        last_interaction: chat.last_interaction,
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
+async fn messages(
+  db: &Surreal<Any>,
+  timeout: Duration,
+  summary: &mut ArchiveSummary,
+) -> super::Result<Vec<Record>> {
+  #[derive(serde::Deserialize)]
+  struct OutMessage {
+    id: Thing,
+    chat: Option<Thing>,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    sender: String,
+    content: String,
+    embedding: Option<Vec<f32>>,
+  }
+
+  let query = r#"
+    SELECT
+      *,
+      (->posted_in->chat.id)[0] AS chat
+    FROM message
+    ORDER BY timestamp, id;
+  "#;
+
+  let messages = db
+    .query(query)
+    .with_timeout(timeout)
+    .await?
+    .take::<Vec<OutMessage>>(0)?;
+
+  let mut records = Vec::new();
+  for message in messages {
+    let id = message.id.id.to_raw();
+    let Some(chat) = message.chat else {
+      tracing::warn!("Skipped message {id} because it isn't in a chat");
+      summary.skipped = summary.skipped.saturating_add(1);
+      continue;
+    };
+
+    records.push(Record::Message {
+      id,
+      chat: chat.id.to_raw(),
+      timestamp: message.timestamp,
+      sender: message.sender,
+      content: message.content,
+      embedding: message.embedding,
+    });
+  }
+
+  Ok(records)
+}
+
+async fn files(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+  timeout: Duration,
+) -> super::Result<Vec<Record>> {
+  #[derive(serde::Deserialize)]
+  struct OutFile {
+    id: Thing,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    data: Bytes,
+    title: String,
+    extension: String,
+    description: String,
+    embedding: Option<Vec<f32>>,
+  }
+
+  let files = db
+    .query("SELECT * FROM file ORDER BY timestamp, id;")
+    .with_timeout(timeout)
+    .await?
+    .take::<Vec<OutFile>>(0)?;
+
+  Ok(
+    files
+      .into_iter()
+      .map(|file| Record::File {
+        store,
+        id: file.id.id.to_raw(),
+        timestamp: file.timestamp,
+        title: file.title,
+        extension: file.extension,
+        description: file.description,
+        data: BASE64_STANDARD.encode(file.data.into_inner()),
+        embedding: file.embedding,
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
+async fn attachments(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+  timeout: Duration,
+) -> super::Result<Vec<Record>> {
+  #[derive(serde::Deserialize)]
+  struct OutAttachment {
+    file: Thing,
+    target: Thing,
+  }
+
+  let attachments = db
+    .query("SELECT in AS file, out AS target FROM attached_to;")
+    .with_timeout(timeout)
+    .await?
+    .take::<Vec<OutAttachment>>(0)?;
+
+  Ok(
+    attachments
+      .into_iter()
+      .map(|attachment| Record::Attachment {
+        store,
+        file: attachment.file.id.to_raw(),
+        target: attachment.target.id.to_raw(),
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
+async fn memos(
+  db: &Surreal<Any>,
+  timeout: Duration,
+) -> super::Result<Vec<Record>> {
+  #[derive(serde::Deserialize)]
+  struct OutMemo {
+    id: Thing,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    content: String,
+    embedding: Option<Vec<f32>>,
+  }
+
+  let memos = db
+    .query("SELECT * FROM memo ORDER BY timestamp, id;")
+    .with_timeout(timeout)
+    .await?
+    .take::<Vec<OutMemo>>(0)?;
+
+  Ok(
+    memos
+      .into_iter()
+      .map(|memo| Record::Memo {
+        id: memo.id.id.to_raw(),
+        timestamp: memo.timestamp,
+        content: memo.content,
+        embedding: memo.embedding,
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
+async fn journal_entries(
+  db: &Surreal<Any>,
+  timeout: Duration,
+) -> super::Result<Vec<Record>> {
+  #[derive(serde::Deserialize)]
+  struct OutJournalEntry {
+    id: Thing,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    content: String,
+  }
+
+  let entries = db
+    .query("SELECT * FROM journal ORDER BY timestamp, id;")
+    .with_timeout(timeout)
+    .await?
+    .take::<Vec<OutJournalEntry>>(0)?;
+
+  Ok(
+    entries
+      .into_iter()
+      .map(|entry| Record::JournalEntry {
+        id: entry.id.id.to_raw(),
+        timestamp: entry.timestamp,
+        content: entry.content,
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
+fn write_error(err: impl std::fmt::Display) -> super::Error {
+  super::Error::Archive(format!("Writing archive failed: {err}"))
+}
@@ -1,19 +1,26 @@
-use include_dir::include_dir;
-use std::{env, path::PathBuf};
-use surrealdb_migrations::MigrationRunner;
+use std::{env, path::PathBuf, time::Duration};
 
 use surrealdb::{
-  engine::any::Any, opt::auth::Root, sql::Thing, RecordId, Surreal,
+  engine::any::Any,
+  sql::{Bytes, Thing},
+  RecordId, Surreal,
 };
 
+use super::connection::{ConnectOptions, Connection, WithTimeout};
+
+#[derive(Clone)]
 pub struct Client {
-  private: Surreal<Any>,
-  public: Surreal<Any>,
+  private: Connection,
+  public: Connection,
+  embedding_dimension: usize,
+  query_timeout: Duration,
 }
 
+pub(crate) const KNN_EF: usize = 40;
+
 pub async fn connect(
   config: super::config::ClientConfig,
-) -> anyhow::Result<Client> {
+) -> super::Result<Client> {
   Client::new(config).await
 }
 
@@ -33,6 +40,22 @@ pub struct Message {
   pub sender: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct File {
+  pub id: String,
+  pub timestamp: chrono::DateTime<chrono::Utc>,
+  pub data: Vec<u8>,
+  pub title: String,
+  pub extension: String,
+  pub description: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Page<T: Clone> {
+  pub records: Vec<T>,
+  pub next: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct FullTextSearch<T: Clone> {
   pub record: T,
@@ -40,8 +63,14 @@ pub struct FullTextSearch<T: Clone> {
   pub score: f32,
 }
 
+#[derive(Debug, Clone)]
+pub struct VectorSearch<T: Clone> {
+  pub record: T,
+  pub distance: f32,
+}
+
 impl Client {
-  pub async fn insert_chat(&self) -> anyhow::Result<Chat> {
+  pub async fn insert_chat(&self) -> super::Result<Chat> {
     #[derive(serde::Serialize)]
     struct InChat {
       timestamp: Option<chrono::DateTime<chrono::Utc>>,
@@ -57,13 +86,15 @@ impl Client {
 
     let chat = self
       .public
+      .db()
       .create::<Option<OutChat>>("chat")
       .content(InChat {
         timestamp: None,
         last_interaction: None,
       })
+      .with_timeout(self.query_timeout)
       .await?
-      .ok_or_else(|| anyhow::anyhow!("Database returned none"))?;
+      .ok_or(super::Error::NoRecord("chat"))?;
 
     Ok(Chat {
       id: chat.id.id.to_raw(),
@@ -72,12 +103,68 @@ impl Client {
     })
   }
 
+  pub async fn list_chats(&self) -> super::Result<Vec<Chat>> {
+    #[derive(serde::Deserialize)]
+    struct OutChat {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      last_interaction: Option<chrono::DateTime<chrono::Utc>>,
+    }
+
+    let query = r#"
+      SELECT *
+      FROM chat
+      ORDER BY last_interaction DESC, timestamp DESC;
+    "#;
+
+    let chats = self
+      .public
+      .db()
+      .query(query)
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutChat>>(0)?;
+
+    Ok(
+      chats
+        .into_iter()
+        .map(|chat| Chat {
+          id: chat.id.id.to_raw(),
+          timestamp: chat.timestamp,
+          last_interaction: chat.last_interaction,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn get_chat(&self, chat: String) -> super::Result<Option<Chat>> {
+    #[derive(serde::Deserialize)]
+    struct OutChat {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      last_interaction: Option<chrono::DateTime<chrono::Utc>>,
+    }
+
+    let chat = self
+      .public
+      .db()
+      .select::<Option<OutChat>>(("chat", chat))
+      .with_timeout(self.query_timeout)
+      .await?;
+
+    Ok(chat.map(|chat| Chat {
+      id: chat.id.id.to_raw(),
+      timestamp: chat.timestamp,
+      last_interaction: chat.last_interaction,
+    }))
+  }
+
   pub async fn insert_message(
     &self,
     chat: String,
     sender: String,
     content: String,
-  ) -> anyhow::Result<Message> {
+  ) -> super::Result<Message> {
     #[derive(serde::Serialize)]
     struct InMessage {
       timestamp: Option<chrono::DateTime<chrono::Utc>>,
@@ -102,6 +189,7 @@ impl Client {
 
     let mut response = self
       .public
+      .db()
       .query(
         r#"
           BEGIN;
@@ -120,18 +208,19 @@ impl Client {
         },
       ))
       .bind(("chat", RecordId::from(("chat", chat))))
+      .with_timeout(self.query_timeout)
       .await?;
 
     let message = response
       .take::<Vec<OutMessage>>(1)?
       .into_iter()
       .nth(0)
-      .ok_or_else(|| anyhow::anyhow!("Database returned none"))?;
+      .ok_or(super::Error::NoRecord("message"))?;
     let posted_in = response
       .take::<Vec<OutPostedIn>>(2)?
       .into_iter()
       .nth(0)
-      .ok_or_else(|| anyhow::anyhow!("Database returned none"))?;
+      .ok_or(super::Error::NoRecord("posted_in"))?;
 
     Ok(Message {
       id: posted_in.message.key().to_string(),
@@ -142,10 +231,239 @@ impl Client {
     })
   }
 
+  /// Replace the content of a message and drop its now stale embedding
+  pub async fn update_message_content(
+    &self,
+    message: String,
+    content: String,
+  ) -> super::Result<Message> {
+    #[derive(serde::Deserialize)]
+    struct OutMessage {
+      id: Thing,
+      chat: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      sender: String,
+    }
+
+    let query = r#"
+      BEGIN;
+      UPDATE $message SET content = $content, embedding = NONE;
+      SELECT
+        *,
+        (->posted_in->chat.id)[0] AS chat
+      FROM $message;
+      COMMIT;
+    "#;
+
+    let message = self
+      .public
+      .db()
+      .query(query)
+      .bind(("message", RecordId::from(("message", message.clone()))))
+      .bind(("content", content))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMessage>>(1)?
+      .into_iter()
+      .nth(0)
+      .ok_or(super::Error::NotFound {
+        table: "message",
+        id: message,
+      })?;
+
+    Ok(Message {
+      id: message.id.id.to_raw(),
+      chat: message.chat.id.to_raw(),
+      timestamp: message.timestamp,
+      content: message.content,
+      sender: message.sender,
+    })
+  }
+
+  pub async fn delete_message(&self, message: String) -> super::Result<()> {
+    let query = r#"
+      BEGIN;
+      LET $chats = (SELECT VALUE out FROM posted_in WHERE in = $message);
+      DELETE attached_to WHERE out = $message;
+      DELETE posted_in WHERE in = $message;
+      DELETE $message;
+      FOR $chat IN $chats {
+        UPDATE $chat SET
+          last_interaction = array::max(<-posted_in<-message.timestamp);
+      };
+      COMMIT;
+    "#;
+
+    self
+      .public
+      .db()
+      .query(query)
+      .bind(("message", RecordId::from(("message", message))))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn delete_chat(&self, chat: String) -> super::Result<()> {
+    let query = r#"
+      BEGIN;
+      LET $messages = (SELECT VALUE in FROM posted_in WHERE out = $chat);
+      DELETE attached_to WHERE out IN $messages;
+      DELETE posted_in WHERE out = $chat;
+      DELETE message WHERE id IN $messages;
+      DELETE $chat;
+      COMMIT;
+    "#;
+
+    self
+      .public
+      .db()
+      .query(query)
+      .bind(("chat", RecordId::from(("chat", chat))))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn list_messages(
+    &self,
+    chat: String,
+    cursor: Option<String>,
+    limit: usize,
+  ) -> super::Result<Page<Message>> {
+    #[derive(serde::Deserialize)]
+    struct OutMessage {
+      id: Thing,
+      chat: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      sender: String,
+    }
+
+    let query = r#"
+      LET $messages = (SELECT VALUE <-posted_in<-message FROM ONLY $chat);
+      LET $after = IF $cursor != NONE THEN
+        (SELECT id, timestamp FROM ONLY $cursor)
+      END;
+      SELECT
+        *,
+        (->posted_in->chat.id)[0] AS chat
+      FROM $messages
+      WHERE $after = NONE
+        OR timestamp > $after.timestamp
+        OR (timestamp = $after.timestamp AND id > $after.id)
+      ORDER BY timestamp, id
+      LIMIT $limit;
+    "#;
+
+    let messages = self
+      .public
+      .db()
+      .query(query)
+      .bind(("chat", RecordId::from(("chat", chat))))
+      .bind((
+        "cursor",
+        cursor.map(|cursor| RecordId::from(("message", cursor))),
+      ))
+      .bind(("limit", limit))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMessage>>(2)?;
+
+    let records = messages
+      .into_iter()
+      .map(|message| Message {
+        id: message.id.id.to_raw(),
+        chat: message.chat.id.to_raw(),
+        timestamp: message.timestamp,
+        content: message.content,
+        sender: message.sender,
+      })
+      .collect::<Vec<_>>();
+    let next = if records.len() < limit {
+      None
+    } else {
+      records.last().map(|message| message.id.clone())
+    };
+
+    Ok(Page { records, next })
+  }
+
   pub async fn search_messages(
+    &self,
+    query: impl Into<super::search::SearchQuery>,
+  ) -> super::Result<Vec<FullTextSearch<Message>>> {
+    #[derive(serde::Deserialize)]
+    struct OutMessage {
+      id: Thing,
+      chat: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      sender: String,
+      highlights: String,
+      score: f32,
+    }
+
+    let query = query.into();
+    let filter_clause = super::search::MESSAGE_FILTER;
+    let order_clause = query.order.clause();
+    let statement = format!(
+      r#"
+        SELECT
+          *,
+          (->posted_in->chat.id)[0] AS chat,
+          search::highlight($prefix, $suffix, 1) AS highlights,
+          search::score(1) AS score
+        FROM message
+        WHERE content @1@ $content AND {filter_clause}
+        ORDER BY {order_clause}
+        LIMIT $limit
+        START $offset;
+      "#
+    );
+
+    let messages = query
+      .filter
+      .bind(self.public.db().query(statement))
+      .bind(("content", query.content))
+      .bind(("prefix", query.highlight_prefix))
+      .bind(("suffix", query.highlight_suffix))
+      .bind(("limit", query.limit))
+      .bind(("offset", query.offset))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMessage>>(0)?;
+
+    Ok(
+      messages
+        .into_iter()
+        .map(|message| FullTextSearch {
+          record: Message {
+            id: message.id.id.to_raw(),
+            chat: message.chat.id.to_raw(),
+            timestamp: message.timestamp,
+            content: message.content,
+            sender: message.sender,
+          },
+          highlights: message.highlights,
+          score: message.score,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn hybrid_search_messages(
     &self,
     content: &str,
-  ) -> anyhow::Result<Vec<FullTextSearch<Message>>> {
+    embedding: Vec<f32>,
+    filter: super::search::MessageFilter,
+    limit: usize,
+  ) -> super::Result<Vec<super::search::HybridSearch<Message>>> {
     #[derive(serde::Deserialize)]
     struct OutMessage {
       id: Thing,
@@ -153,31 +471,505 @@ impl Client {
       timestamp: chrono::DateTime<chrono::Utc>,
       content: String,
       sender: String,
+    }
+
+    self.check_embedding(&embedding)?;
+    check_neighbours(limit)?;
+
+    let candidates = limit.saturating_mul(2);
+    let filter_clause = super::search::MESSAGE_FILTER;
+    let projected_filter_clause = super::search::PROJECTED_MESSAGE_FILTER;
+    let query = format!(
+      r#"
+        SELECT
+          *,
+          (->posted_in->chat.id)[0] AS chat,
+          search::score(1) AS score
+        FROM message
+        WHERE content @1@ $content AND {filter_clause}
+        ORDER BY score DESC
+        LIMIT $candidates;
+      "#
+    );
+
+    let lexical = filter
+      .bind(self.public.db().query(query))
+      .bind(("content", content.to_owned()))
+      .bind(("candidates", candidates))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMessage>>(0)?;
+
+    // NOTE: nearest messages are filtered after they are fetched so more of
+    // them are fetched until enough pass the filter or none are left
+    let mut nearest = candidates;
+    let mut semantic = loop {
+      // NOTE: knn operator parameters have to be literals
+      let ef = nearest.max(KNN_EF);
+      let query = format!(
+        r#"
+          LET $nearest = (
+            SELECT
+              *,
+              (->posted_in->chat.id)[0] AS chat,
+              vector::distance::knn() AS distance
+            FROM message
+            WHERE embedding <|{nearest},{ef}|> $embedding
+          );
+          RETURN array::len($nearest);
+          SELECT * FROM $nearest
+          WHERE {projected_filter_clause}
+          ORDER BY distance;
+        "#
+      );
+
+      let mut response = filter
+        .bind(self.public.db().query(query))
+        .bind(("embedding", embedding.clone()))
+        .with_timeout(self.query_timeout)
+        .await?;
+      let fetched = response.take::<Option<usize>>(1)?.unwrap_or_default();
+      let semantic = response.take::<Vec<OutMessage>>(2)?;
+      if semantic.len() >= candidates || fetched < nearest {
+        break semantic;
+      }
+      nearest = nearest.saturating_mul(2);
+    };
+    semantic.truncate(candidates);
+
+    let into_ranked = |messages: Vec<OutMessage>| {
+      messages
+        .into_iter()
+        .map(|message| {
+          let id = message.id.id.to_raw();
+          (
+            id.clone(),
+            Message {
+              id,
+              chat: message.chat.id.to_raw(),
+              timestamp: message.timestamp,
+              content: message.content,
+              sender: message.sender,
+            },
+          )
+        })
+        .collect::<Vec<_>>()
+    };
+
+    Ok(super::search::fuse(
+      into_ranked(lexical),
+      into_ranked(semantic),
+      limit,
+    ))
+  }
+
+  pub async fn insert_file(
+    &self,
+    title: String,
+    extension: String,
+    description: String,
+    data: Vec<u8>,
+  ) -> super::Result<File> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+    }
+
+    let query = r#"
+      CREATE ONLY file SET
+        data = $data,
+        title = $title,
+        extension = $extension,
+        description = $description;
+    "#;
+
+    let file = self
+      .public
+      .db()
+      .query(query)
+      .bind(("data", bytes(data)))
+      .bind(("title", title))
+      .bind(("extension", extension))
+      .bind(("description", description))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Option<OutFile>>(0)?
+      .ok_or(super::Error::NoRecord("file"))?;
+
+    Ok(File {
+      id: file.id.id.to_raw(),
+      timestamp: file.timestamp,
+      data: file.data.into_inner(),
+      title: file.title,
+      extension: file.extension,
+      description: file.description,
+    })
+  }
+
+  pub async fn attach_file_to_message(
+    &self,
+    file: String,
+    message: String,
+  ) -> super::Result<()> {
+    self
+      .public
+      .db()
+      .query("RELATE $file->attached_to->$message;")
+      .bind(("file", RecordId::from(("file", file))))
+      .bind(("message", RecordId::from(("message", message))))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn get_file(&self, file: String) -> super::Result<Option<File>> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+    }
+
+    let file = self
+      .public
+      .db()
+      .select::<Option<OutFile>>(("file", file))
+      .with_timeout(self.query_timeout)
+      .await?;
+
+    Ok(file.map(|file| File {
+      id: file.id.id.to_raw(),
+      timestamp: file.timestamp,
+      data: file.data.into_inner(),
+      title: file.title,
+      extension: file.extension,
+      description: file.description,
+    }))
+  }
+
+  pub async fn list_files_for_message(
+    &self,
+    message: String,
+  ) -> super::Result<Vec<File>> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+    }
+
+    let query = r#"
+      LET $files = (SELECT VALUE <-attached_to<-file FROM ONLY $message);
+      SELECT *
+      FROM $files
+      ORDER BY timestamp;
+    "#;
+
+    let files = self
+      .public
+      .db()
+      .query(query)
+      .bind(("message", RecordId::from(("message", message))))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutFile>>(1)?;
+
+    Ok(
+      files
+        .into_iter()
+        .map(|file| File {
+          id: file.id.id.to_raw(),
+          timestamp: file.timestamp,
+          data: file.data.into_inner(),
+          title: file.title,
+          extension: file.extension,
+          description: file.description,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  /// Search file titles and descriptions ignoring the message filter
+  pub async fn search_files(
+    &self,
+    query: impl Into<super::search::SearchQuery>,
+  ) -> super::Result<Vec<FullTextSearch<File>>> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
       highlights: String,
       score: f32,
     }
 
+    let query = query.into();
+    let order_clause = query.order.clause();
+    // NOTE: highlights are of the title unless only the description matched
+    let search = format!(
+      r#"
+        SELECT
+          *,
+          IF search::highlight($prefix, $suffix, 1) != title THEN
+            search::highlight($prefix, $suffix, 1)
+          ELSE
+            search::highlight($prefix, $suffix, 2)
+          END AS highlights,
+          search::score(1) + search::score(2) AS score
+        FROM file
+        WHERE title @1@ $query OR description @2@ $query
+        ORDER BY {order_clause}
+        LIMIT $limit
+        START $offset;
+      "#
+    );
+
+    let files = self
+      .public
+      .db()
+      .query(search)
+      .bind(("query", query.content))
+      .bind(("prefix", query.highlight_prefix))
+      .bind(("suffix", query.highlight_suffix))
+      .bind(("limit", query.limit))
+      .bind(("offset", query.offset))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutFile>>(0)?;
+
+    Ok(
+      files
+        .into_iter()
+        .map(|file| FullTextSearch {
+          record: File {
+            id: file.id.id.to_raw(),
+            timestamp: file.timestamp,
+            data: file.data.into_inner(),
+            title: file.title,
+            extension: file.extension,
+            description: file.description,
+          },
+          highlights: file.highlights,
+          score: file.score,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub fn embedding_dimension(&self) -> usize {
+    self.embedding_dimension
+  }
+
+  pub async fn set_message_embedding(
+    &self,
+    message: String,
+    embedding: Vec<f32>,
+  ) -> super::Result<()> {
+    self.check_embedding(&embedding)?;
+
+    self
+      .public
+      .db()
+      .query("UPDATE $message SET embedding = $embedding;")
+      .bind(("message", RecordId::from(("message", message))))
+      .bind(("embedding", embedding))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn set_file_embedding(
+    &self,
+    file: String,
+    embedding: Vec<f32>,
+  ) -> super::Result<()> {
+    self.check_embedding(&embedding)?;
+
+    self
+      .public
+      .db()
+      .query("UPDATE $file SET embedding = $embedding;")
+      .bind(("file", RecordId::from(("file", file))))
+      .bind(("embedding", embedding))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn clear_embeddings(&self) -> super::Result<()> {
+    let query = r#"
+      UPDATE message SET embedding = NONE;
+      UPDATE file SET embedding = NONE;
+    "#;
+
+    self
+      .public
+      .db()
+      .query(query)
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn list_unembedded_messages(
+    &self,
+    limit: usize,
+  ) -> super::Result<Vec<Message>> {
+    #[derive(serde::Deserialize)]
+    struct OutMessage {
+      id: Thing,
+      chat: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      sender: String,
+    }
+
     let query = r#"
       SELECT
         *,
-        (->posted_in->chat.id)[0] AS chat,
-        search::highlight('<b>', '</b>', 1) AS highlights,
-        search::score(1) AS score
+        (->posted_in->chat.id)[0] AS chat
       FROM message
-      WHERE content @1@ $content;
+      WHERE embedding = NONE
+      ORDER BY timestamp, id
+      LIMIT $limit;
     "#;
 
     let messages = self
       .public
+      .db()
       .query(query)
-      .bind(("content", content.to_owned()))
+      .bind(("limit", limit))
+      .with_timeout(self.query_timeout)
       .await?
       .take::<Vec<OutMessage>>(0)?;
 
     Ok(
       messages
         .into_iter()
-        .map(|message| FullTextSearch {
+        .map(|message| Message {
+          id: message.id.id.to_raw(),
+          chat: message.chat.id.to_raw(),
+          timestamp: message.timestamp,
+          content: message.content,
+          sender: message.sender,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn list_unembedded_files(
+    &self,
+    limit: usize,
+  ) -> super::Result<Vec<File>> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+    }
+
+    let query = r#"
+      SELECT *
+      FROM file
+      WHERE embedding = NONE
+      ORDER BY timestamp, id
+      LIMIT $limit;
+    "#;
+
+    let files = self
+      .public
+      .db()
+      .query(query)
+      .bind(("limit", limit))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutFile>>(0)?;
+
+    Ok(
+      files
+        .into_iter()
+        .map(|file| File {
+          id: file.id.id.to_raw(),
+          timestamp: file.timestamp,
+          data: file.data.into_inner(),
+          title: file.title,
+          extension: file.extension,
+          description: file.description,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn nearest_messages(
+    &self,
+    embedding: Vec<f32>,
+    k: usize,
+  ) -> super::Result<Vec<VectorSearch<Message>>> {
+    #[derive(serde::Deserialize)]
+    struct OutMessage {
+      id: Thing,
+      chat: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      sender: String,
+      distance: f32,
+    }
+
+    self.check_embedding(&embedding)?;
+    check_neighbours(k)?;
+
+    // NOTE: knn operator parameters have to be literals
+    let ef = k.max(KNN_EF);
+    let query = format!(
+      r#"
+        SELECT
+          *,
+          (->posted_in->chat.id)[0] AS chat,
+          vector::distance::knn() AS distance
+        FROM message
+        WHERE embedding <|{k},{ef}|> $embedding
+        ORDER BY distance;
+      "#
+    );
+
+    let messages = self
+      .public
+      .db()
+      .query(query)
+      .bind(("embedding", embedding))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMessage>>(0)?;
+
+    Ok(
+      messages
+        .into_iter()
+        .map(|message| VectorSearch {
           record: Message {
             id: message.id.id.to_raw(),
             chat: message.chat.id.to_raw(),
@@ -185,30 +977,231 @@ impl Client {
             content: message.content,
             sender: message.sender,
           },
-          highlights: message.highlights,
-          score: message.score,
+          distance: message.distance,
         })
         .collect::<Vec<_>>(),
     )
   }
 
-  pub async fn migrate(&self) -> anyhow::Result<()> {
-    if let Err(err) = MigrationRunner::new(&self.private)
-      .load_files(&include_dir!("$CARGO_MANIFEST_DIR/migrations/private"))
-      .up()
-      .await
-    {
-      let err = err.to_string();
-      return Err(anyhow::anyhow!(format!("Private migration failed: {err}")));
+  pub async fn nearest_files(
+    &self,
+    embedding: Vec<f32>,
+    k: usize,
+  ) -> super::Result<Vec<VectorSearch<File>>> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+      distance: f32,
     }
 
-    if let Err(err) = MigrationRunner::new(&self.public)
-      .load_files(&include_dir!("$CARGO_MANIFEST_DIR/migrations/public"))
-      .up()
-      .await
+    self.check_embedding(&embedding)?;
+    check_neighbours(k)?;
+
+    // NOTE: knn operator parameters have to be literals
+    let ef = k.max(KNN_EF);
+    let query = format!(
+      r#"
+        SELECT
+          *,
+          vector::distance::knn() AS distance
+        FROM file
+        WHERE embedding <|{k},{ef}|> $embedding
+        ORDER BY distance;
+      "#
+    );
+
+    let files = self
+      .public
+      .db()
+      .query(query)
+      .bind(("embedding", embedding))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutFile>>(0)?;
+
+    Ok(
+      files
+        .into_iter()
+        .map(|file| VectorSearch {
+          record: File {
+            id: file.id.id.to_raw(),
+            timestamp: file.timestamp,
+            data: file.data.into_inner(),
+            title: file.title,
+            extension: file.extension,
+            description: file.description,
+          },
+          distance: file.distance,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub fn private_store(&self) -> super::private::PrivateStore {
+    super::private::PrivateStore::new(
+      self.private.clone(),
+      self.embedding_dimension,
+      self.query_timeout,
+    )
+  }
+
+  /// Check that both databases respond
+  pub async fn health(&self) -> super::Result<()> {
+    self.private.health().await?;
+    self.public.health().await
+  }
+
+  /// Reconnect to both databases and sign in again
+  ///
+  /// Does nothing for embedded and memory databases
+  pub async fn reconnect(&self) -> super::Result<()> {
+    self.private.reconnect().await?;
+    self.public.reconnect().await
+  }
+
+  pub async fn migrate(&self) -> super::Result<()> {
+    self.migrate_up(None).await
+  }
+
+  pub async fn migration_status(
+    &self,
+  ) -> super::Result<Vec<super::migration::MigrationStatus>> {
+    let mut status = Vec::new();
+    for store in super::migration::MigrationStore::ALL {
+      status.extend(super::migration::status(&self.store(store), store).await?);
+    }
+
+    Ok(status)
+  }
+
+  /// Apply migrations up to and including `to` or all when not set
+  ///
+  /// Stores without `to` are migrated up to their latest migration named
+  /// before it.
+  pub async fn migrate_up(&self, to: Option<&str>) -> super::Result<()> {
+    let targets = match to {
+      Some(to) => self.migration_targets(to)?,
+      None => super::migration::MigrationStore::ALL
+        .into_iter()
+        .map(|store| (store, super::migration::known(store).pop()))
+        .collect(),
+    };
+    for (store, target) in targets {
+      let Some(target) = target else {
+        continue;
+      };
+      super::migration::up(&self.store(store), store, Some(&target)).await?;
+      super::migration::define_embedding_indexes(
+        &self.store(store),
+        store,
+        self.embedding_dimension,
+      )
+      .await?;
+    }
+
+    Ok(())
+  }
+
+  /// Revert migrations applied after `to` or all of them when initial
+  ///
+  /// Stores without `to` are reverted to their latest migration named
+  /// before it.
+  pub async fn migrate_down(&self, to: &str) -> super::Result<()> {
+    for (store, target) in self.migration_targets(to)? {
+      let target = target
+        .unwrap_or_else(|| super::migration::INITIAL_MIGRATION.to_string());
+      super::migration::down(&self.store(store), store, &target).await?;
+    }
+
+    Ok(())
+  }
+
+  /// Write chats, messages, files and their relations as JSON Lines
+  ///
+  /// Memos, journal entries and private files are only written when
+  /// `private` is set
+  pub async fn export(
+    &self,
+    writer: impl std::io::Write,
+    private: bool,
+  ) -> super::Result<super::archive::ArchiveSummary> {
+    let private = private.then(|| self.private.db());
+    super::archive::export(
+      &self.public.db(),
+      private.as_ref(),
+      self.query_timeout,
+      writer,
+    )
+    .await
+  }
+
+  /// Restore an archive written by [`Client::export`] into migrated databases
+  ///
+  /// Records keep their ids so importing the same archive again changes
+  /// nothing. Private records are only restored when `private` is set and
+  /// embeddings of another dimension are dropped.
+  pub async fn import(
+    &self,
+    reader: impl std::io::BufRead,
+    private: bool,
+  ) -> super::Result<super::archive::ArchiveSummary> {
+    let private = private.then(|| self.private.db());
+    super::archive::import(
+      &self.public.db(),
+      private.as_ref(),
+      self.query_timeout,
+      self.embedding_dimension,
+      reader,
+    )
+    .await
+  }
+
+  fn store(&self, store: super::migration::MigrationStore) -> Surreal<Any> {
+    match store {
+      super::migration::MigrationStore::Private => self.private.db(),
+      super::migration::MigrationStore::Public => self.public.db(),
+    }
+  }
+
+  /// Latest migration of each store named up to and including `to`
+  fn migration_targets(
+    &self,
+    to: &str,
+  ) -> super::Result<Vec<(super::migration::MigrationStore, Option<String>)>> {
+    let known = super::migration::MigrationStore::ALL
+      .into_iter()
+      .map(|store| (store, super::migration::known(store)))
+      .collect::<Vec<_>>();
+    if to != super::migration::INITIAL_MIGRATION
+      && !known.iter().any(|(_, names)| names.iter().any(|name| name == to))
     {
-      let err = err.to_string();
-      return Err(anyhow::anyhow!(format!("Public migration failed: {err}")));
+      return Err(super::Error::Migration(format!("Unknown migration {to}")));
+    }
+
+    // NOTE: migration names start with their timestamp so they sort in the
+    // order they were written in
+    Ok(
+      known
+        .into_iter()
+        .map(|(store, names)| {
+          let target = names.into_iter().rfind(|name| name.as_str() <= to);
+          (store, target)
+        })
+        .collect(),
+    )
+  }
+
+  fn check_embedding(&self, embedding: &[f32]) -> super::Result<()> {
+    if embedding.len() != self.embedding_dimension {
+      return Err(super::Error::EmbeddingDimension {
+        expected: self.embedding_dimension,
+        actual: embedding.len(),
+      });
     }
 
     Ok(())
@@ -216,68 +1209,198 @@ impl Client {
 
   pub(crate) async fn new(
     config: super::config::ClientConfig,
-  ) -> anyhow::Result<Self> {
-    let is_memory =
-      matches!(config.connection, crate::config::ConnectionConfig::Memory);
+  ) -> super::Result<Self> {
+    // NOTE: supervision would loop without ever sleeping
+    if config.health_check_interval == 0 || config.reconnect_backoff == 0 {
+      return Err(super::Error::Config(
+        "Health check interval and reconnect backoff need to be positive"
+          .to_string(),
+      ));
+    }
+
+    let is_websocket = matches!(
+      config.connection,
+      crate::config::ConnectionConfig::Websocket(_)
+    );
+    let is_embedded = matches!(
+      config.connection,
+      crate::config::ConnectionConfig::SurrealKv(_)
+        | crate::config::ConnectionConfig::RocksDb(_)
+    );
+    let mut tls = None;
     let address = match config.connection {
-      crate::config::ConnectionConfig::Embedded(
+      crate::config::ConnectionConfig::SurrealKv(
+        crate::config::EmbeddedConnectionConfig { path },
+      ) => {
+        format!("surrealkv:{}", storage_path(path, "surrealkv")?)
+      }
+      #[cfg(feature = "rocksdb")]
+      crate::config::ConnectionConfig::RocksDb(
         crate::config::EmbeddedConnectionConfig { path },
       ) => {
-        let path = match path
-          .and_then(|path| path.to_str().map(|path| path.to_owned()))
-        {
-          Some(path) => path,
-          None => {
-            let project_dirs = directories::ProjectDirs::from(
-              env!("QUALIFIER"),
-              env!("ORGANIZATION"),
-              "nebulon",
-            )
-            .ok_or_else(|| anyhow::anyhow!("no project directories"))?;
-            PathBuf::from(project_dirs.project_path())
-              .join("data")
-              .to_str()
-              .ok_or_else(|| anyhow::anyhow!("failed creating database"))?
-              .to_owned()
-          }
-        };
-        format!("rocksdb:{path}")
+        format!("rocksdb:{}", storage_path(path, "rocksdb")?)
+      }
+      #[cfg(not(feature = "rocksdb"))]
+      crate::config::ConnectionConfig::RocksDb(_) => {
+        return Err(super::Error::Config(
+          "RocksDB connections need nebulon built with the rocksdb feature"
+            .to_string(),
+        ));
       }
       crate::config::ConnectionConfig::Websocket(
-        crate::config::WebsocketConnectionConfig { host, port },
+        crate::config::WebsocketConnectionConfig {
+          host,
+          port,
+          tls: false,
+          ..
+        },
       ) => {
         format!("ws://{host}:{port}")
       }
+      crate::config::ConnectionConfig::Websocket(
+        crate::config::WebsocketConnectionConfig {
+          host,
+          port,
+          tls: true,
+          ca,
+        },
+      ) => {
+        if let Some(ca) = ca {
+          tls = Some(tls_config(&ca)?);
+        }
+        format!("wss://{host}:{port}")
+      }
       crate::config::ConnectionConfig::Memory => "mem://".to_string(),
     };
 
-    let user = config.auth.user;
-    let pass = config.auth.pass;
+    // NOTE: embedded databases have no users to sign in as
+    let auth = if is_websocket {
+      Some(credentials(config.auth)?)
+    } else {
+      None
+    };
+    let connect_timeout = Duration::from_secs(config.connect_timeout);
+    let query_timeout = Duration::from_secs(config.query_timeout);
+    // NOTE: an embedded store can only be opened once so each database gets
+    // its own directory
+    let options = |database: &str| ConnectOptions {
+      address: if is_embedded {
+        format!("{address}/{database}")
+      } else {
+        address.clone()
+      },
+      tls: tls.clone(),
+      auth: auth.clone(),
+      namespace: config.namespace.clone(),
+      database: database.to_string(),
+      connect_timeout,
+      query_timeout,
+      reconnect: is_websocket,
+    };
+
+    let private = Connection::open(options(&config.private_database)).await?;
+    let public = Connection::open(options(&config.public_database)).await?;
 
-    let private = surrealdb::engine::any::connect(address.clone()).await?;
-    if !is_memory {
-      private
-        .signin(Root {
-          username: user.as_str(),
-          password: pass.as_str(),
-        })
-        .await?;
-    }
-    private.use_ns("double_star").await?;
-    private.use_db("private").await?;
-
-    let public = surrealdb::engine::any::connect(address).await?;
-    if !is_memory {
-      public
-        .signin(Root {
-          username: user.as_str(),
-          password: pass.as_str(),
-        })
-        .await?;
+    if is_websocket {
+      let interval = Duration::from_secs(config.health_check_interval);
+      let backoff = Duration::from_millis(config.reconnect_backoff);
+      let max_backoff = Duration::from_millis(config.reconnect_backoff_max);
+      private.supervise(interval, backoff, max_backoff);
+      public.supervise(interval, backoff, max_backoff);
     }
-    public.use_ns("double_star").await?;
-    public.use_db("public").await?;
 
-    Ok(Self { private, public })
+    Ok(Self {
+      private,
+      public,
+      query_timeout,
+      embedding_dimension: config.embedding_dimension,
+    })
+  }
+}
+
+/// Knn operators fail without any neighbours to find
+pub(crate) fn check_neighbours(k: usize) -> super::Result<()> {
+  if k == 0 {
+    return Err(super::Error::NoNeighbours);
+  }
+
+  Ok(())
+}
+
+/// Bytes bound as a database value because serializing them corrupts records
+pub(crate) fn bytes(data: Vec<u8>) -> surrealdb::sql::Value {
+  surrealdb::sql::Value::Bytes(Bytes::from(data))
+}
+
+/// Embedded database path or `dir` in the project directory when not set
+fn storage_path(path: Option<PathBuf>, dir: &str) -> super::Result<String> {
+  if let Some(path) = path.and_then(|path| path.to_str().map(ToOwned::to_owned))
+  {
+    return Ok(path);
+  }
+
+  let project_dirs = directories::ProjectDirs::from(
+    env!("QUALIFIER"),
+    env!("ORGANIZATION"),
+    "nebulon",
+  )
+  .ok_or_else(|| super::Error::Storage("No project directories".to_string()))?;
+  PathBuf::from(project_dirs.project_path())
+    .join(dir)
+    .to_str()
+    .map(ToOwned::to_owned)
+    .ok_or_else(|| super::Error::Storage("Invalid data directory".to_string()))
+}
+
+fn credentials(
+  auth: super::config::AuthConfig,
+) -> super::Result<super::connection::Credentials> {
+  let pass = match auth.pass_file {
+    Some(path) => std::fs::read_to_string(&path)
+      .map_err(|err| {
+        super::Error::Config(format!(
+          "Failed reading password file {path:?}: {err}"
+        ))
+      })?
+      .trim_end_matches(['\r', '\n'])
+      .to_owned(),
+    None => auth.pass,
+  };
+
+  if matches!(auth.auth_level, super::config::AuthLevel::Record)
+    && auth.access.is_none()
+  {
+    return Err(super::Error::Config(
+      "Record users need an access method".to_string(),
+    ));
   }
+
+  Ok(super::connection::Credentials {
+    level: auth.auth_level,
+    user: auth.user,
+    pass,
+    access: auth.access,
+  })
+}
+
+fn tls_config(ca: &std::path::Path) -> super::Result<rustls::ClientConfig> {
+  let invalid = |err: std::io::Error| {
+    super::Error::Config(format!("Failed reading CA file {ca:?}: {err}"))
+  };
+
+  let mut reader = std::io::BufReader::new(
+    std::fs::File::open(ca).map_err(invalid)?,
+  );
+  let mut roots = rustls::RootCertStore::empty();
+  for certificate in rustls_pemfile::certs(&mut reader) {
+    roots.add(certificate.map_err(invalid)?).map_err(|err| {
+      super::Error::Config(format!("Invalid certificate in {ca:?}: {err}"))
+    })?;
+  }
+
+  Ok(
+    rustls::ClientConfig::builder()
+      .with_root_certificates(roots)
+      .with_no_client_auth(),
+  )
 }
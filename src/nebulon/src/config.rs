@@ -1,8 +1,54 @@
 #[derive(clap::Args)]
-pub struct FromArgs {}
+pub struct FromArgs {
+  #[clap(subcommand)]
+  pub command: Option<Command>,
+}
 
 impl gravity::config::FromArgs for FromArgs {}
 
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum Command {
+  /// Manage database migrations
+  Migrate {
+    #[clap(subcommand)]
+    command: MigrateCommand,
+  },
+  /// Write the databases to a JSON Lines archive
+  Export {
+    /// Archive path or stdout when not set
+    path: Option<std::path::PathBuf>,
+    /// Also export memos, journal entries and private files
+    #[clap(long, action)]
+    private: bool,
+  },
+  /// Restore a JSON Lines archive into the databases
+  Import {
+    /// Archive path or stdin when not set
+    path: Option<std::path::PathBuf>,
+    /// Also import memos, journal entries and private files
+    #[clap(long, action)]
+    private: bool,
+  },
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum MigrateCommand {
+  /// List embedded and applied migrations
+  Status,
+  /// Apply migrations
+  Up {
+    /// Last migration to apply instead of all of them
+    #[clap(long)]
+    to: Option<String>,
+  },
+  /// Revert migrations
+  Down {
+    /// Migration to revert to or "0" to revert all of them
+    #[clap(long)]
+    to: String,
+  },
+}
+
 #[derive(Default, serde::Deserialize)]
 pub struct FromEnv {
   #[serde(flatten)]
@@ -20,6 +66,7 @@ impl gravity::config::FromFile for FromFile {}
 
 #[derive(Clone)]
 pub struct Config {
+  pub command: Option<Command>,
   pub client: ClientConfig,
 }
 
@@ -30,8 +77,11 @@ impl gravity::config::Values for Config {
 
   type TFile = FromFile;
 
-  fn new(_: Self::TArgs, env: Self::TEnv) -> Self {
-    Self { client: env.client }
+  fn new(args: Self::TArgs, env: Self::TEnv) -> Self {
+    Self {
+      command: args.command,
+      client: env.client,
+    }
   }
 
   fn import(&mut self, _: Self::TFile) {}
@@ -41,33 +91,251 @@ impl gravity::config::Values for Config {
   }
 }
 
-#[derive(Default, Clone, serde::Deserialize)]
+#[derive(derivative::Derivative, Clone, serde::Deserialize)]
+#[derivative(Default)]
 pub struct ClientConfig {
   #[serde(flatten)]
   pub auth: AuthConfig,
   #[serde(flatten)]
   pub connection: ConnectionConfig,
+  /// Namespace both databases are in
+  #[derivative(Default(value = "\"double_star\".to_string()"))]
+  #[serde(default = "default_namespace")]
+  pub namespace: String,
+  /// Database of the agent private store
+  #[derivative(Default(value = "\"private\".to_string()"))]
+  #[serde(default = "default_private_database")]
+  pub private_database: String,
+  /// Database of chats, messages and files
+  #[derivative(Default(value = "\"public\".to_string()"))]
+  #[serde(default = "default_public_database")]
+  pub public_database: String,
+  #[derivative(Default(value = "384"))]
+  #[serde(
+    default = "default_embedding_dimension",
+    deserialize_with = "gravity::config::deserialize_from_str"
+  )]
+  pub embedding_dimension: usize,
+  /// Seconds to wait for connecting and signing in
+  #[derivative(Default(value = "10"))]
+  #[serde(
+    default = "default_connect_timeout",
+    deserialize_with = "gravity::config::deserialize_from_str"
+  )]
+  pub connect_timeout: u64,
+  /// Seconds to wait for a query to complete
+  #[derivative(Default(value = "30"))]
+  #[serde(
+    default = "default_query_timeout",
+    deserialize_with = "gravity::config::deserialize_from_str"
+  )]
+  pub query_timeout: u64,
+  /// Seconds between websocket connection health checks
+  #[derivative(Default(value = "10"))]
+  #[serde(
+    default = "default_health_check_interval",
+    deserialize_with = "gravity::config::deserialize_from_str"
+  )]
+  pub health_check_interval: u64,
+  /// Milliseconds to wait before retrying a failed reconnect
+  #[derivative(Default(value = "500"))]
+  #[serde(
+    default = "default_reconnect_backoff",
+    deserialize_with = "gravity::config::deserialize_from_str"
+  )]
+  pub reconnect_backoff: u64,
+  /// Maximum milliseconds the reconnect backoff doubles up to
+  #[derivative(Default(value = "30000"))]
+  #[serde(
+    default = "default_reconnect_backoff_max",
+    deserialize_with = "gravity::config::deserialize_from_str"
+  )]
+  pub reconnect_backoff_max: u64,
+}
+
+fn default_namespace() -> String {
+  "double_star".to_string()
+}
+
+fn default_private_database() -> String {
+  "private".to_string()
+}
+
+fn default_public_database() -> String {
+  "public".to_string()
+}
+
+fn default_embedding_dimension() -> usize {
+  384
+}
+
+fn default_connect_timeout() -> u64 {
+  10
+}
+
+fn default_query_timeout() -> u64 {
+  30
+}
+
+fn default_health_check_interval() -> u64 {
+  10
+}
+
+fn default_reconnect_backoff() -> u64 {
+  500
+}
+
+fn default_reconnect_backoff_max() -> u64 {
+  30000
+}
+
+fn default_user() -> String {
+  "double_star".to_string()
+}
+
+fn default_pass() -> String {
+  "double_star".to_string()
 }
 
 #[derive(derivative::Derivative, Clone, serde::Deserialize)]
 #[derivative(Default)]
 pub struct AuthConfig {
   #[derivative(Default(value = "\"double_star\".to_string()"))]
+  #[serde(default = "default_user")]
   pub user: String,
   #[derivative(Default(value = "\"double_star\".to_string()"))]
+  #[serde(default = "default_pass")]
   pub pass: String,
+  /// File to read the password from instead of `pass`
+  pub pass_file: Option<std::path::PathBuf>,
+  /// Level the user is defined on
+  #[serde(default)]
+  pub auth_level: AuthLevel,
+  /// Record access method record users sign in with
+  pub access: Option<String>,
+}
+
+#[derive(Default, Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthLevel {
+  /// Root user with access to every namespace
+  #[default]
+  Root,
+  /// User defined on the configured namespace
+  Namespace,
+  /// User defined on each configured database
+  Database,
+  /// Record user signing in with `user` and `pass` parameters through the
+  /// configured access method on each database
+  Record,
 }
 
 #[derive(derivative::Derivative, Clone, serde::Deserialize)]
 #[derivative(Default)]
-#[serde(untagged)]
+#[serde(try_from = "RawConnectionConfig")]
 pub enum ConnectionConfig {
   #[derivative(Default)]
   Websocket(WebsocketConnectionConfig),
-  Embedded(EmbeddedConnectionConfig),
+  SurrealKv(EmbeddedConnectionConfig),
+  RocksDb(EmbeddedConnectionConfig),
+  Memory,
+}
+
+#[derive(Default, Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionKind {
+  #[default]
+  #[serde(rename = "ws")]
+  Websocket,
+  SurrealKv,
+  RocksDb,
   Memory,
 }
 
+// NOTE: env values arrive as strings and every field of every connection is
+// accepted here so that fields of another connection are reported
+#[derive(serde::Deserialize)]
+struct RawConnectionConfig {
+  #[serde(default)]
+  connection: ConnectionKind,
+  host: Option<String>,
+  port: Option<String>,
+  tls: Option<String>,
+  ca: Option<std::path::PathBuf>,
+  path: Option<std::path::PathBuf>,
+}
+
+impl TryFrom<RawConnectionConfig> for ConnectionConfig {
+  type Error = String;
+
+  fn try_from(raw: RawConnectionConfig) -> Result<Self, Self::Error> {
+    let kind = raw.connection;
+    let reject = |field: &str, set: bool| {
+      if set {
+        Err(format!("{field} is not used by {kind:?} connections"))
+      } else {
+        Ok(())
+      }
+    };
+
+    match kind {
+      ConnectionKind::Websocket => {
+        reject("path", raw.path.is_some())?;
+
+        let defaults = WebsocketConnectionConfig::default();
+        let port = match raw.port {
+          Some(port) => port
+            .parse::<u32>()
+            .map_err(|err| format!("Invalid port {port}: {err}"))?,
+          None => defaults.port,
+        };
+        let tls = match raw.tls {
+          Some(tls) => tls
+            .parse::<bool>()
+            .map_err(|err| format!("Invalid tls {tls}: {err}"))?,
+          None => defaults.tls,
+        };
+
+        if !tls {
+          reject("ca", raw.ca.is_some())?;
+        }
+
+        Ok(Self::Websocket(WebsocketConnectionConfig {
+          host: raw.host.unwrap_or(defaults.host),
+          port,
+          tls,
+          ca: raw.ca,
+        }))
+      }
+      ConnectionKind::SurrealKv
+      | ConnectionKind::RocksDb
+      | ConnectionKind::Memory => {
+        reject("host", raw.host.is_some())?;
+        reject("port", raw.port.is_some())?;
+        reject("tls", raw.tls.is_some())?;
+        reject("ca", raw.ca.is_some())?;
+
+        match kind {
+          ConnectionKind::SurrealKv => {
+            return Ok(Self::SurrealKv(EmbeddedConnectionConfig {
+              path: raw.path,
+            }));
+          }
+          ConnectionKind::RocksDb => {
+            return Ok(Self::RocksDb(EmbeddedConnectionConfig {
+              path: raw.path,
+            }));
+          }
+          _ => {}
+        }
+
+        reject("path", raw.path.is_some())?;
+        Ok(Self::Memory)
+      }
+    }
+  }
+}
+
 #[derive(derivative::Derivative, Clone, serde::Deserialize)]
 #[derivative(Default)]
 pub struct WebsocketConnectionConfig {
@@ -75,6 +343,12 @@ pub struct WebsocketConnectionConfig {
   pub host: String,
   #[derivative(Default(value = "8000"))]
   pub port: u32,
+  /// Connect over `wss://` instead of `ws://`
+  #[serde(default)]
+  pub tls: bool,
+  /// PEM file with certificates of authorities trusted instead of the
+  /// system ones when using TLS
+  pub ca: Option<std::path::PathBuf>,
 }
 
 #[derive(Default, Clone, serde::Deserialize)]
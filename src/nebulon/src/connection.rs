@@ -0,0 +1,242 @@
+use std::future::{Future, IntoFuture};
+use std::sync::{Arc, PoisonError, RwLock, Weak};
+use std::time::Duration;
+
+use surrealdb::{
+  engine::any::Any,
+  opt::auth::{Database, Namespace, Record, Root},
+  Surreal,
+};
+
+/// Database handle that gets replaced when reconnecting
+#[derive(Clone)]
+pub(crate) struct Connection {
+  inner: Arc<Inner>,
+}
+
+pub(crate) struct ConnectOptions {
+  pub(crate) address: String,
+  pub(crate) tls: Option<rustls::ClientConfig>,
+  pub(crate) auth: Option<Credentials>,
+  pub(crate) namespace: String,
+  pub(crate) database: String,
+  pub(crate) connect_timeout: Duration,
+  pub(crate) query_timeout: Duration,
+  /// Embedded and memory databases can't be reopened while still in use
+  pub(crate) reconnect: bool,
+}
+
+#[derive(Clone)]
+pub(crate) struct Credentials {
+  pub(crate) level: super::config::AuthLevel,
+  pub(crate) user: String,
+  pub(crate) pass: String,
+  pub(crate) access: Option<String>,
+}
+
+struct Inner {
+  db: RwLock<Surreal<Any>>,
+  options: ConnectOptions,
+}
+
+impl Connection {
+  pub(crate) async fn open(options: ConnectOptions) -> super::Result<Self> {
+    let db = connect(&options).await?;
+
+    Ok(Self {
+      inner: Arc::new(Inner {
+        db: RwLock::new(db),
+        options,
+      }),
+    })
+  }
+
+  pub(crate) fn db(&self) -> Surreal<Any> {
+    self
+      .inner
+      .db
+      .read()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clone()
+  }
+
+  pub(crate) async fn health(&self) -> super::Result<()> {
+    self
+      .db()
+      .health()
+      .with_timeout(self.inner.options.query_timeout)
+      .await
+  }
+
+  /// Connect again and replace the handle used by every clone
+  pub(crate) async fn reconnect(&self) -> super::Result<()> {
+    if !self.inner.options.reconnect {
+      return Ok(());
+    }
+
+    let db = connect(&self.inner.options).await?;
+    *self
+      .inner
+      .db
+      .write()
+      .unwrap_or_else(PoisonError::into_inner) = db;
+
+    Ok(())
+  }
+
+  /// Check health every `interval` and reconnect with exponential backoff
+  /// until every clone of this connection is dropped
+  pub(crate) fn supervise(
+    &self,
+    interval: Duration,
+    backoff: Duration,
+    max_backoff: Duration,
+  ) {
+    let inner = Arc::downgrade(&self.inner);
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(interval).await;
+        let Some(connection) = upgrade(&inner) else {
+          return;
+        };
+        let Err(err) = connection.health().await else {
+          continue;
+        };
+        tracing::warn!("Database health check failed so reconnecting: {err}");
+        drop(connection);
+
+        let mut delay = backoff;
+        loop {
+          let Some(connection) = upgrade(&inner) else {
+            return;
+          };
+          match connection.reconnect().await {
+            Ok(()) => {
+              tracing::info!("Reconnected to database");
+              break;
+            }
+            Err(err) => {
+              tracing::warn!(
+                "Reconnecting failed so retrying in {delay:?}: {err}"
+              );
+            }
+          }
+          drop(connection);
+
+          tokio::time::sleep(delay).await;
+          delay = delay.saturating_mul(2).min(max_backoff);
+        }
+      }
+    });
+  }
+}
+
+fn upgrade(inner: &Weak<Inner>) -> Option<Connection> {
+  inner.upgrade().map(|inner| Connection { inner })
+}
+
+async fn connect(options: &ConnectOptions) -> super::Result<Surreal<Any>> {
+  let connect = async {
+    let db = match &options.tls {
+      Some(tls) => {
+        surrealdb::engine::any::connect((
+          options.address.clone(),
+          surrealdb::opt::Config::new().rustls(tls.clone()),
+        ))
+        .await
+      }
+      None => surrealdb::engine::any::connect(options.address.clone()).await,
+    }
+    .map_err(|err| super::Error::Connection(Box::new(err)))?;
+    if let Some(credentials) = &options.auth {
+      signin(&db, options, credentials)
+        .await
+        .map_err(|err| super::Error::Auth(Box::new(err)))?;
+    }
+    // NOTE: the client replays only the last use when it reconnects by itself
+    // so the namespace and database are used together
+    db.use_ns(options.namespace.clone())
+      .use_db(options.database.clone())
+      .await?;
+
+    Ok(db)
+  };
+
+  match tokio::time::timeout(options.connect_timeout, connect).await {
+    Ok(result) => result,
+    Err(_elapsed) => Err(super::Error::Timeout(options.connect_timeout)),
+  }
+}
+
+async fn signin(
+  db: &Surreal<Any>,
+  options: &ConnectOptions,
+  credentials: &Credentials,
+) -> surrealdb::Result<()> {
+  #[derive(serde::Serialize)]
+  struct RecordParams<'a> {
+    user: &'a str,
+    pass: &'a str,
+  }
+
+  let namespace = options.namespace.as_str();
+  let database = options.database.as_str();
+  let username = credentials.user.as_str();
+  let password = credentials.pass.as_str();
+  match credentials.level {
+    super::config::AuthLevel::Root => {
+      db.signin(Root { username, password }).await?;
+    }
+    super::config::AuthLevel::Namespace => {
+      db.signin(Namespace {
+        namespace,
+        username,
+        password,
+      })
+      .await?;
+    }
+    super::config::AuthLevel::Database => {
+      db.signin(Database {
+        namespace,
+        database,
+        username,
+        password,
+      })
+      .await?;
+    }
+    super::config::AuthLevel::Record => {
+      db.signin(Record {
+        namespace,
+        database,
+        access: credentials.access.as_deref().unwrap_or_default(),
+        params: RecordParams {
+          user: username,
+          pass: password,
+        },
+      })
+      .await?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Fail database requests that don't complete within a timeout
+pub(crate) trait WithTimeout<T> {
+  fn with_timeout(
+    self,
+    timeout: Duration,
+  ) -> impl Future<Output = super::Result<T>>;
+}
+
+impl<F, T> WithTimeout<T> for F
+where
+  F: IntoFuture<Output = surrealdb::Result<T>>,
+{
+  async fn with_timeout(self, timeout: Duration) -> super::Result<T> {
+    match tokio::time::timeout(timeout, self.into_future()).await {
+      Ok(result) => Ok(result?),
+      Err(_elapsed) => Err(super::Error::Timeout(timeout)),
+    }
+  }
+}
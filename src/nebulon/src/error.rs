@@ -0,0 +1,95 @@
+use surrealdb::error::{Api, Db};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// NOTE: database errors are boxed to keep results small
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  /// The database can't be reached or the connection dropped
+  #[error("Database connection failed: {0}")]
+  Connection(#[source] Box<surrealdb::Error>),
+  /// The database didn't respond in time
+  #[error("Database didn't respond within {0:?}")]
+  Timeout(std::time::Duration),
+  /// The database rejected the credentials or the session expired
+  #[error("Database authentication failed: {0}")]
+  Auth(#[source] Box<surrealdb::Error>),
+  /// A record that was expected to exist doesn't
+  #[error("Record {table}:{id} not found")]
+  NotFound { table: &'static str, id: String },
+  /// A write was rejected by the schema or a unique index
+  #[error("Database constraint violated: {0}")]
+  Constraint(#[source] Box<surrealdb::Error>),
+  /// Migrating failed or the database schema doesn't match the binary
+  #[error("Migration failed: {0}")]
+  Migration(String),
+  /// An embedding doesn't have the configured number of dimensions
+  #[error("Embedding has {actual} dimensions instead of {expected}")]
+  EmbeddingDimension { expected: usize, actual: usize },
+  /// A nearest neighbour search asked for no neighbours
+  #[error("Nearest neighbour search needs at least one neighbour")]
+  NoNeighbours,
+  /// The client config is incomplete or references unreadable files
+  #[error("Invalid database config: {0}")]
+  Config(String),
+  /// Local database storage couldn't be located
+  #[error("Database storage unavailable: {0}")]
+  Storage(String),
+  /// The database didn't return a record it should have returned
+  #[error("Database returned no {0} record")]
+  NoRecord(&'static str),
+  /// An archive couldn't be read or written or has an unsupported version
+  #[error("Archive failed: {0}")]
+  Archive(String),
+  /// Any other database error
+  #[error("Database error: {0}")]
+  Database(#[source] Box<surrealdb::Error>),
+}
+
+/// Variant wrapping a database error
+type Kind = fn(Box<surrealdb::Error>) -> Error;
+
+impl From<surrealdb::Error> for Error {
+  fn from(err: surrealdb::Error) -> Self {
+    let kind: Option<Kind> = match &err {
+      surrealdb::Error::Db(
+        Db::InvalidAuth | Db::ExpiredSession | Db::ExpiredToken,
+      ) => Some(Self::Auth),
+      surrealdb::Error::Db(
+        Db::FieldCheck { .. }
+        | Db::FieldValue { .. }
+        | Db::IndexExists { .. }
+        | Db::RecordExists { .. },
+      ) => Some(Self::Constraint),
+      surrealdb::Error::Api(
+        Api::Ws(_)
+        | Api::Http(_)
+        | Api::ConnectionUninitialised
+        | Api::InvalidUrl(_),
+      ) => Some(Self::Connection),
+      surrealdb::Error::Api(Api::Query(message)) => remote_kind(message),
+      _ => None,
+    };
+
+    kind.unwrap_or(Self::Database)(Box::new(err))
+  }
+}
+
+/// Database error messages classified like the errors they come from
+const REMOTE_MESSAGES: &[(&[&str], Kind)] = &[
+  (&["There was a problem with authentication"], Error::Auth),
+  (&["The session has expired"], Error::Auth),
+  (&["The token has expired"], Error::Auth),
+  (&["Database record `", "` already exists"], Error::Constraint),
+  (&["Database index `", "` already contains "], Error::Constraint),
+  (&[" for field `", "`, but expected a "], Error::Constraint),
+  (&[" for field `", "`, but field must conform to: "], Error::Constraint),
+];
+
+// NOTE: remote engines only send the message of the database error
+fn remote_kind(message: &str) -> Option<Kind> {
+  REMOTE_MESSAGES
+    .iter()
+    .find(|(parts, _)| parts.iter().all(|part| message.contains(part)))
+    .map(|(_, kind)| *kind)
+}
@@ -6,5 +6,13 @@
 #![deny(clippy::unreachable)]
 #![deny(clippy::allow_attributes_without_reason)]
 
+pub mod archive;
 pub mod client;
 pub mod config;
+mod connection;
+pub mod error;
+pub mod migration;
+pub mod private;
+pub mod search;
+
+pub use error::{Error, Result};
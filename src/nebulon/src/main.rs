@@ -6,6 +6,8 @@
 #![deny(clippy::unreachable)]
 #![deny(clippy::allow_attributes_without_reason)]
 
+use std::io::Write;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
   let config = gravity::config::new_async::<nebulon::config::Config>(
@@ -15,12 +17,59 @@ async fn main() -> anyhow::Result<()> {
     "nebulon",
     concat!(env!("CARGO_PKG_REPOSITORY"), "/src/nebulon"),
   )
-  .await;
+  .await?;
+
+  let values = config.values_async().await;
+  let client = nebulon::client::connect(values.client.clone()).await?;
 
-  let client =
-    nebulon::client::connect(config.values_async().await.client.clone())
-      .await?;
-  client.migrate().await?;
+  match values.command {
+    Some(nebulon::config::Command::Migrate { command }) => match command {
+      nebulon::config::MigrateCommand::Status => {
+        let mut stdout = std::io::stdout().lock();
+        for status in client.migration_status().await? {
+          let state = match (status.applied, status.known) {
+            (true, true) => "applied",
+            (false, true) => "pending",
+            (_, false) => "unknown",
+          };
+          writeln!(stdout, "{} {} {}", status.store, status.name, state)?;
+        }
+      }
+      nebulon::config::MigrateCommand::Up { to } => {
+        client.migrate_up(to.as_deref()).await?;
+      }
+      nebulon::config::MigrateCommand::Down { to } => {
+        client.migrate_down(&to).await?;
+      }
+    },
+    Some(nebulon::config::Command::Export { path, private }) => {
+      let summary = match path {
+        Some(path) => {
+          let file = std::fs::File::create(path)?;
+          client.export(std::io::BufWriter::new(file), private).await?
+        }
+        None => {
+          let stdout = std::io::BufWriter::new(std::io::stdout());
+          client.export(stdout, private).await?
+        }
+      };
+      tracing::info!("Exported {summary}");
+    }
+    Some(nebulon::config::Command::Import { path, private }) => {
+      client.migrate().await?;
+      let summary = match path {
+        Some(path) => {
+          let file = std::fs::File::open(path)?;
+          client.import(std::io::BufReader::new(file), private).await?
+        }
+        None => client.import(std::io::stdin().lock(), private).await?,
+      };
+      tracing::info!("Imported {summary}");
+    }
+    None => {
+      client.migrate().await?;
+    }
+  }
 
   Ok(())
 }
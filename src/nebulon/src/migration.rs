@@ -0,0 +1,251 @@
+use include_dir::{include_dir, Dir};
+use surrealdb::{engine::any::Any, Surreal};
+use surrealdb_migrations::MigrationRunner;
+
+static PRIVATE_MIGRATIONS: Dir<'static> =
+  include_dir!("$CARGO_MANIFEST_DIR/migrations/private");
+static PUBLIC_MIGRATIONS: Dir<'static> =
+  include_dir!("$CARGO_MANIFEST_DIR/migrations/public");
+
+/// Migration name that reverts every migration when migrating down
+pub const INITIAL_MIGRATION: &str = "0";
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationStore {
+  Private,
+  Public,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+  pub store: MigrationStore,
+  pub name: String,
+  /// Migration was applied to the database
+  pub applied: bool,
+  /// Migration is embedded in this binary
+  pub known: bool,
+}
+
+impl MigrationStore {
+  pub const ALL: [Self; 2] = [Self::Private, Self::Public];
+
+  fn files(&self) -> &'static Dir<'static> {
+    match self {
+      Self::Private => &PRIVATE_MIGRATIONS,
+      Self::Public => &PUBLIC_MIGRATIONS,
+    }
+  }
+
+  /// Tables with an embedding index
+  fn embedded_tables(&self) -> &'static [&'static str] {
+    match self {
+      Self::Private => &["file", "memo"],
+      Self::Public => &["file", "message"],
+    }
+  }
+}
+
+impl std::fmt::Display for MigrationStore {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Private => write!(f, "private"),
+      Self::Public => write!(f, "public"),
+    }
+  }
+}
+
+/// Names of up migrations embedded in this binary in order
+pub(crate) fn known(store: MigrationStore) -> Vec<String> {
+  let mut names = store
+    .files()
+    .get_dir("migrations")
+    .map(|dir| {
+      dir
+        .files()
+        .filter(|file| {
+          file.path().extension().and_then(|ext| ext.to_str())
+            == Some("surql")
+        })
+        .filter_map(|file| file.path().file_stem()?.to_str())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  names.sort();
+  names
+}
+
+/// Names of migrations applied to the database in order
+pub(crate) async fn applied(
+  db: &Surreal<Any>,
+) -> super::Result<Vec<String>> {
+  let mut names = db
+    .query("SELECT VALUE script_name FROM script_migration;")
+    .await?
+    .take::<Vec<String>>(0)?;
+  names.sort();
+  Ok(names)
+}
+
+pub(crate) async fn status(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+) -> super::Result<Vec<MigrationStatus>> {
+  let known = known(store);
+  let applied = applied(db).await?;
+
+  let mut names = known.iter().chain(applied.iter()).collect::<Vec<_>>();
+  names.sort();
+  names.dedup();
+
+  Ok(
+    names
+      .into_iter()
+      .map(|name| MigrationStatus {
+        store,
+        name: name.clone(),
+        applied: applied.contains(name),
+        known: known.contains(name),
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
+/// Fail when the database has migrations this binary doesn't know about
+pub(crate) async fn check(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+) -> super::Result<()> {
+  let unknown = status(db, store)
+    .await?
+    .into_iter()
+    .filter(|status| status.applied && !status.known)
+    .map(|status| status.name)
+    .collect::<Vec<_>>();
+  if !unknown.is_empty() {
+    return Err(super::Error::Migration(format!(
+      "The {store} database schema is newer than this binary \
+        with unknown migrations {}",
+      unknown.join(", ")
+    )));
+  }
+
+  Ok(())
+}
+
+pub(crate) async fn up(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+  to: Option<&str>,
+) -> super::Result<()> {
+  check(db, store).await?;
+
+  // NOTE: the schema is applied again even without pending migrations which
+  // conflicts in reopened surrealkv databases
+  let applied = applied(db).await?;
+  let pending = known(store)
+    .into_iter()
+    .filter(|name| to.is_none_or(|to| name.as_str() <= to))
+    .any(|name| !applied.contains(&name));
+  if !pending && !applied.is_empty() {
+    return Ok(());
+  }
+
+  let runner = MigrationRunner::new(db);
+  let runner = runner.load_files(store.files());
+  let result = match to {
+    Some(to) => runner.up_to(to).await,
+    None => runner.up().await,
+  };
+  if let Err(err) = result {
+    let err = err.to_string();
+    return Err(super::Error::Migration(format!(
+      "Migrating {store} database failed: {err}"
+    )));
+  }
+
+  Ok(())
+}
+
+/// Define embedding indexes unless they were defined for `dimension` already
+///
+/// Embeddings of another dimension are removed so they can be recomputed
+/// and don't fail building the indexes.
+pub(crate) async fn define_embedding_indexes(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+  dimension: usize,
+) -> super::Result<()> {
+  let defined = db
+    .query("RETURN embedding_index:current.dimension;")
+    .await?
+    .take::<Option<usize>>(0)?;
+  if defined == Some(dimension) {
+    return Ok(());
+  }
+
+  tracing::info!(
+    "Defining {store} database embedding indexes for dimension {dimension}"
+  );
+
+  // NOTE: statements run one by one because transactions defining indexes
+  // conflict in surrealkv databases and every statement can run again
+  for table in store.embedded_tables() {
+    db.query(format!(
+      r#"
+        UPDATE {table} SET embedding = NONE
+          WHERE embedding != NONE AND array::len(embedding) != $dimension;
+      "#
+    ))
+    .bind(("dimension", dimension))
+    .await?
+    .check()?;
+
+    // NOTE: the index dimension has to be a literal
+    db.query(format!(
+      r#"
+        DEFINE INDEX OVERWRITE {table}_embedding ON {table} FIELDS embedding
+          HNSW DIMENSION {dimension} DIST EUCLIDEAN EFC 150 M 12;
+      "#
+    ))
+    .await?
+    .check()?;
+  }
+
+  db.query(
+    r#"
+      UPSERT embedding_index:current SET
+        dimension = $dimension,
+        timestamp = time::now();
+    "#,
+  )
+  .bind(("dimension", dimension))
+  .await?
+  .check()?;
+
+  Ok(())
+}
+
+pub(crate) async fn down(
+  db: &Surreal<Any>,
+  store: MigrationStore,
+  to: &str,
+) -> super::Result<()> {
+  check(db, store).await?;
+
+  if let Err(err) = MigrationRunner::new(db)
+    .load_files(store.files())
+    .down(to)
+    .await
+  {
+    let err = err.to_string();
+    return Err(super::Error::Migration(format!(
+      "Migrating {store} database failed: {err}"
+    )));
+  }
+
+  Ok(())
+}
@@ -0,0 +1,486 @@
+use std::time::Duration;
+
+use surrealdb::{
+  sql::{Bytes, Thing},
+  RecordId,
+};
+
+use super::connection::{Connection, WithTimeout};
+
+use super::client::{File, FullTextSearch, VectorSearch};
+
+/// Agent private space kept apart from the public chat history
+#[derive(Clone)]
+pub struct PrivateStore {
+  connection: Connection,
+  embedding_dimension: usize,
+  query_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Memo {
+  pub id: String,
+  pub timestamp: chrono::DateTime<chrono::Utc>,
+  pub content: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+  pub id: String,
+  pub timestamp: chrono::DateTime<chrono::Utc>,
+  pub content: String,
+}
+
+impl PrivateStore {
+  pub async fn insert_memo(&self, content: String) -> super::Result<Memo> {
+    #[derive(serde::Serialize)]
+    struct InMemo {
+      timestamp: Option<chrono::DateTime<chrono::Utc>>,
+      content: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct OutMemo {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+    }
+
+    let memo = self
+      .connection
+      .db()
+      .create::<Option<OutMemo>>("memo")
+      .content(InMemo {
+        timestamp: None,
+        content,
+      })
+      .with_timeout(self.query_timeout)
+      .await?
+      .ok_or(super::Error::NoRecord("memo"))?;
+
+    Ok(Memo {
+      id: memo.id.id.to_raw(),
+      timestamp: memo.timestamp,
+      content: memo.content,
+    })
+  }
+
+  pub async fn list_memos(&self) -> super::Result<Vec<Memo>> {
+    #[derive(serde::Deserialize)]
+    struct OutMemo {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+    }
+
+    let memos = self
+      .connection
+      .db()
+      .query("SELECT * FROM memo ORDER BY timestamp;")
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMemo>>(0)?;
+
+    Ok(
+      memos
+        .into_iter()
+        .map(|memo| Memo {
+          id: memo.id.id.to_raw(),
+          timestamp: memo.timestamp,
+          content: memo.content,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  /// Search memos ignoring the message filter
+  pub async fn search_memos(
+    &self,
+    query: impl Into<super::search::SearchQuery>,
+  ) -> super::Result<Vec<FullTextSearch<Memo>>> {
+    #[derive(serde::Deserialize)]
+    struct OutMemo {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      highlights: String,
+      score: f32,
+    }
+
+    let query = query.into();
+    let order_clause = query.order.clause();
+    let statement = format!(
+      r#"
+        SELECT
+          *,
+          search::highlight($prefix, $suffix, 1) AS highlights,
+          search::score(1) AS score
+        FROM memo
+        WHERE content @1@ $content
+        ORDER BY {order_clause}
+        LIMIT $limit
+        START $offset;
+      "#
+    );
+
+    let memos = self
+      .connection
+      .db()
+      .query(statement)
+      .bind(("content", query.content))
+      .bind(("prefix", query.highlight_prefix))
+      .bind(("suffix", query.highlight_suffix))
+      .bind(("limit", query.limit))
+      .bind(("offset", query.offset))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMemo>>(0)?;
+
+    Ok(
+      memos
+        .into_iter()
+        .map(|memo| FullTextSearch {
+          record: Memo {
+            id: memo.id.id.to_raw(),
+            timestamp: memo.timestamp,
+            content: memo.content,
+          },
+          highlights: memo.highlights,
+          score: memo.score,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn set_memo_embedding(
+    &self,
+    memo: String,
+    embedding: Vec<f32>,
+  ) -> super::Result<()> {
+    self.check_embedding(&embedding)?;
+
+    self
+      .connection
+      .db()
+      .query("UPDATE $memo SET embedding = $embedding;")
+      .bind(("memo", RecordId::from(("memo", memo))))
+      .bind(("embedding", embedding))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn nearest_memos(
+    &self,
+    embedding: Vec<f32>,
+    k: usize,
+  ) -> super::Result<Vec<VectorSearch<Memo>>> {
+    #[derive(serde::Deserialize)]
+    struct OutMemo {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      distance: f32,
+    }
+
+    self.check_embedding(&embedding)?;
+    super::client::check_neighbours(k)?;
+
+    // NOTE: knn operator parameters have to be literals
+    let ef = k.max(super::client::KNN_EF);
+    let query = format!(
+      r#"
+        SELECT
+          *,
+          vector::distance::knn() AS distance
+        FROM memo
+        WHERE embedding <|{k},{ef}|> $embedding
+        ORDER BY distance;
+      "#
+    );
+
+    let memos = self
+      .connection
+      .db()
+      .query(query)
+      .bind(("embedding", embedding))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutMemo>>(0)?;
+
+    Ok(
+      memos
+        .into_iter()
+        .map(|memo| VectorSearch {
+          record: Memo {
+            id: memo.id.id.to_raw(),
+            timestamp: memo.timestamp,
+            content: memo.content,
+          },
+          distance: memo.distance,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn insert_journal_entry(
+    &self,
+    content: String,
+  ) -> super::Result<JournalEntry> {
+    #[derive(serde::Serialize)]
+    struct InJournalEntry {
+      timestamp: Option<chrono::DateTime<chrono::Utc>>,
+      content: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct OutJournalEntry {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+    }
+
+    let entry = self
+      .connection
+      .db()
+      .create::<Option<OutJournalEntry>>("journal")
+      .content(InJournalEntry {
+        timestamp: None,
+        content,
+      })
+      .with_timeout(self.query_timeout)
+      .await?
+      .ok_or(super::Error::NoRecord("journal"))?;
+
+    Ok(JournalEntry {
+      id: entry.id.id.to_raw(),
+      timestamp: entry.timestamp,
+      content: entry.content,
+    })
+  }
+
+  pub async fn list_journal_entries(
+    &self,
+  ) -> super::Result<Vec<JournalEntry>> {
+    #[derive(serde::Deserialize)]
+    struct OutJournalEntry {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+    }
+
+    let entries = self
+      .connection
+      .db()
+      .query("SELECT * FROM journal ORDER BY timestamp;")
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutJournalEntry>>(0)?;
+
+    Ok(
+      entries
+        .into_iter()
+        .map(|entry| JournalEntry {
+          id: entry.id.id.to_raw(),
+          timestamp: entry.timestamp,
+          content: entry.content,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  /// Search journal entries ignoring the message filter
+  pub async fn search_journal_entries(
+    &self,
+    query: impl Into<super::search::SearchQuery>,
+  ) -> super::Result<Vec<FullTextSearch<JournalEntry>>> {
+    #[derive(serde::Deserialize)]
+    struct OutJournalEntry {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      content: String,
+      highlights: String,
+      score: f32,
+    }
+
+    let query = query.into();
+    let order_clause = query.order.clause();
+    let statement = format!(
+      r#"
+        SELECT
+          *,
+          search::highlight($prefix, $suffix, 1) AS highlights,
+          search::score(1) AS score
+        FROM journal
+        WHERE content @1@ $content
+        ORDER BY {order_clause}
+        LIMIT $limit
+        START $offset;
+      "#
+    );
+
+    let entries = self
+      .connection
+      .db()
+      .query(statement)
+      .bind(("content", query.content))
+      .bind(("prefix", query.highlight_prefix))
+      .bind(("suffix", query.highlight_suffix))
+      .bind(("limit", query.limit))
+      .bind(("offset", query.offset))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutJournalEntry>>(0)?;
+
+    Ok(
+      entries
+        .into_iter()
+        .map(|entry| FullTextSearch {
+          record: JournalEntry {
+            id: entry.id.id.to_raw(),
+            timestamp: entry.timestamp,
+            content: entry.content,
+          },
+          highlights: entry.highlights,
+          score: entry.score,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub async fn insert_file(
+    &self,
+    title: String,
+    extension: String,
+    description: String,
+    data: Vec<u8>,
+  ) -> super::Result<File> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+    }
+
+    let query = r#"
+      CREATE ONLY file SET
+        data = $data,
+        title = $title,
+        extension = $extension,
+        description = $description;
+    "#;
+
+    let file = self
+      .connection
+      .db()
+      .query(query)
+      .bind(("data", super::client::bytes(data)))
+      .bind(("title", title))
+      .bind(("extension", extension))
+      .bind(("description", description))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Option<OutFile>>(0)?
+      .ok_or(super::Error::NoRecord("file"))?;
+
+    Ok(File {
+      id: file.id.id.to_raw(),
+      timestamp: file.timestamp,
+      data: file.data.into_inner(),
+      title: file.title,
+      extension: file.extension,
+      description: file.description,
+    })
+  }
+
+  pub async fn attach_file_to_memo(
+    &self,
+    file: String,
+    memo: String,
+  ) -> super::Result<()> {
+    self
+      .connection
+      .db()
+      .query("RELATE $file->attached_to->$memo;")
+      .bind(("file", RecordId::from(("file", file))))
+      .bind(("memo", RecordId::from(("memo", memo))))
+      .with_timeout(self.query_timeout)
+      .await?
+      .check()?;
+
+    Ok(())
+  }
+
+  pub async fn list_files_for_memo(
+    &self,
+    memo: String,
+  ) -> super::Result<Vec<File>> {
+    #[derive(serde::Deserialize)]
+    struct OutFile {
+      id: Thing,
+      timestamp: chrono::DateTime<chrono::Utc>,
+      data: Bytes,
+      title: String,
+      extension: String,
+      description: String,
+    }
+
+    let query = r#"
+      LET $files = (SELECT VALUE <-attached_to<-file FROM ONLY $memo);
+      SELECT *
+      FROM $files
+      ORDER BY timestamp;
+    "#;
+
+    let files = self
+      .connection
+      .db()
+      .query(query)
+      .bind(("memo", RecordId::from(("memo", memo))))
+      .with_timeout(self.query_timeout)
+      .await?
+      .take::<Vec<OutFile>>(1)?;
+
+    Ok(
+      files
+        .into_iter()
+        .map(|file| File {
+          id: file.id.id.to_raw(),
+          timestamp: file.timestamp,
+          data: file.data.into_inner(),
+          title: file.title,
+          extension: file.extension,
+          description: file.description,
+        })
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  pub(crate) fn new(
+    connection: Connection,
+    embedding_dimension: usize,
+    query_timeout: Duration,
+  ) -> Self {
+    Self {
+      connection,
+      embedding_dimension,
+      query_timeout,
+    }
+  }
+
+  fn check_embedding(&self, embedding: &[f32]) -> super::Result<()> {
+    if embedding.len() != self.embedding_dimension {
+      return Err(super::Error::EmbeddingDimension {
+        expected: self.embedding_dimension,
+        actual: embedding.len(),
+      });
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,216 @@
+/// Restricts which messages a search returns
+#[derive(Debug, Clone, Default)]
+pub struct MessageFilter {
+  pub chat: Option<String>,
+  pub sender: Option<String>,
+  pub after: Option<chrono::DateTime<chrono::Utc>>,
+  pub before: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Full text search built up from optional parts
+///
+/// Filters only restrict message searches.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+  pub(crate) content: String,
+  pub(crate) filter: MessageFilter,
+  pub(crate) limit: usize,
+  pub(crate) offset: usize,
+  pub(crate) order: SearchOrder,
+  pub(crate) highlight_prefix: String,
+  pub(crate) highlight_suffix: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchOrder {
+  /// Best matches first
+  #[default]
+  Score,
+  /// Most recent messages first
+  Newest,
+  /// Least recent messages first
+  Oldest,
+}
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+impl SearchQuery {
+  pub fn new(content: impl Into<String>) -> Self {
+    Self {
+      content: content.into(),
+      filter: Default::default(),
+      limit: DEFAULT_SEARCH_LIMIT,
+      offset: 0,
+      order: Default::default(),
+      highlight_prefix: "<b>".to_string(),
+      highlight_suffix: "</b>".to_string(),
+    }
+  }
+
+  pub fn limit(mut self, limit: usize) -> Self {
+    self.limit = limit;
+    self
+  }
+
+  pub fn offset(mut self, offset: usize) -> Self {
+    self.offset = offset;
+    self
+  }
+
+  pub fn filter(mut self, filter: MessageFilter) -> Self {
+    self.filter = filter;
+    self
+  }
+
+  pub fn chat(mut self, chat: impl Into<String>) -> Self {
+    self.filter.chat = Some(chat.into());
+    self
+  }
+
+  pub fn sender(mut self, sender: impl Into<String>) -> Self {
+    self.filter.sender = Some(sender.into());
+    self
+  }
+
+  pub fn after(mut self, after: chrono::DateTime<chrono::Utc>) -> Self {
+    self.filter.after = Some(after);
+    self
+  }
+
+  pub fn before(mut self, before: chrono::DateTime<chrono::Utc>) -> Self {
+    self.filter.before = Some(before);
+    self
+  }
+
+  pub fn order(mut self, order: SearchOrder) -> Self {
+    self.order = order;
+    self
+  }
+
+  /// Wrap matched terms in `prefix` and `suffix` instead of `<b>` and `</b>`
+  pub fn highlight(
+    mut self,
+    prefix: impl Into<String>,
+    suffix: impl Into<String>,
+  ) -> Self {
+    self.highlight_prefix = prefix.into();
+    self.highlight_suffix = suffix.into();
+    self
+  }
+}
+
+impl From<&str> for SearchQuery {
+  fn from(content: &str) -> Self {
+    Self::new(content)
+  }
+}
+
+impl From<String> for SearchQuery {
+  fn from(content: String) -> Self {
+    Self::new(content)
+  }
+}
+
+impl SearchOrder {
+  pub(crate) fn clause(&self) -> &'static str {
+    match self {
+      Self::Score => "score DESC, timestamp DESC",
+      Self::Newest => "timestamp DESC, id DESC",
+      Self::Oldest => "timestamp ASC, id ASC",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct HybridSearch<T: Clone> {
+  pub record: T,
+  pub score: f32,
+  pub lexical_rank: Option<usize>,
+  pub semantic_rank: Option<usize>,
+}
+
+// NOTE: the constant from the original reciprocal rank fusion paper
+pub(crate) const RRF_K: f32 = 60.0;
+
+pub(crate) const MESSAGE_FILTER: &str = r#"
+  ($chat = NONE OR ->posted_in->chat CONTAINS $chat)
+  AND ($sender = NONE OR sender = $sender)
+  AND ($after = NONE OR timestamp >= $after)
+  AND ($before = NONE OR timestamp < $before)
+"#;
+
+// NOTE: knn queries return nothing when combined with other conditions so
+// their candidates are filtered on the projected chat afterwards
+pub(crate) const PROJECTED_MESSAGE_FILTER: &str = r#"
+  ($chat = NONE OR chat = $chat)
+  AND ($sender = NONE OR sender = $sender)
+  AND ($after = NONE OR timestamp >= $after)
+  AND ($before = NONE OR timestamp < $before)
+"#;
+
+impl MessageFilter {
+  pub(crate) fn bind<'a, C: surrealdb::Connection>(
+    &self,
+    query: surrealdb::method::Query<'a, C>,
+  ) -> surrealdb::method::Query<'a, C> {
+    query
+      .bind((
+        "chat",
+        self
+          .chat
+          .clone()
+          .map(|chat| surrealdb::RecordId::from(("chat", chat))),
+      ))
+      .bind(("sender", self.sender.clone()))
+      .bind(("after", self.after.map(surrealdb::sql::Datetime::from)))
+      .bind(("before", self.before.map(surrealdb::sql::Datetime::from)))
+  }
+}
+
+/// Fuse two rankings of records keyed by id with reciprocal rank fusion
+pub(crate) fn fuse<T: Clone>(
+  lexical: Vec<(String, T)>,
+  semantic: Vec<(String, T)>,
+  limit: usize,
+) -> Vec<HybridSearch<T>> {
+  let mut fused = Vec::<(String, HybridSearch<T>)>::new();
+
+  for (rank, (id, record)) in lexical.into_iter().enumerate() {
+    fused.push((
+      id,
+      HybridSearch {
+        record,
+        score: 1.0 / (RRF_K + rank as f32 + 1.0),
+        lexical_rank: Some(rank),
+        semantic_rank: None,
+      },
+    ));
+  }
+
+  for (rank, (id, record)) in semantic.into_iter().enumerate() {
+    let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+    match fused.iter_mut().find(|(existing, _)| *existing == id) {
+      Some((_, existing)) => {
+        existing.score += score;
+        existing.semantic_rank = Some(rank);
+      }
+      None => fused.push((
+        id,
+        HybridSearch {
+          record,
+          score,
+          lexical_rank: None,
+          semantic_rank: Some(rank),
+        },
+      )),
+    }
+  }
+
+  let mut fused = fused
+    .into_iter()
+    .map(|(_, result)| result)
+    .collect::<Vec<_>>();
+  fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+  fused.truncate(limit);
+  fused
+}
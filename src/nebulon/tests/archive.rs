@@ -0,0 +1,167 @@
+mod common;
+
+async fn seed(client: &nebulon::client::Client) -> anyhow::Result<()> {
+  let chat = client.insert_chat().await?;
+  let message = client
+    .insert_message(chat.id.clone(), "user".to_string(), "hi".to_string())
+    .await?;
+  let _ = client
+    .insert_message(chat.id, "agent".to_string(), "hello".to_string())
+    .await?;
+  let file = client
+    .insert_file(
+      "notes".to_string(),
+      "txt".to_string(),
+      "some notes".to_string(),
+      vec![0, 1, 2, 255],
+    )
+    .await?;
+  client.attach_file_to_message(file.id, message.id).await?;
+
+  let private = client.private_store();
+  let memo = private.insert_memo("secret plan".to_string()).await?;
+  let _ = private.insert_journal_entry("secret diary".to_string()).await?;
+  let file = private
+    .insert_file(
+      "secret".to_string(),
+      "txt".to_string(),
+      "hidden".to_string(),
+      vec![42],
+    )
+    .await?;
+  private.attach_file_to_memo(file.id, memo.id).await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_export_import() -> anyhow::Result<()> {
+  let source = common::setup().await?;
+  seed(&source).await?;
+
+  let mut archive = Vec::new();
+  let exported = source.export(&mut archive, true).await?;
+
+  let target = common::setup().await?;
+  let imported = target.import(archive.as_slice(), true).await?;
+  let reimported = target.import(archive.as_slice(), true).await?;
+
+  assert_eq!(exported.chats, 1);
+  assert_eq!(exported.messages, 2);
+  assert_eq!(exported.files, 2);
+  assert_eq!(exported.attachments, 2);
+  assert_eq!(exported.memos, 1);
+  assert_eq!(exported.journal_entries, 1);
+  assert_eq!(imported, exported);
+  assert_eq!(reimported, exported);
+
+  let source_chats = source.list_chats().await?;
+  let source_chat = source_chats.first();
+  let target_chats = target.list_chats().await?;
+  let target_chat = target_chats.first();
+  assert_eq!(target_chats.len(), 1);
+  assert_eq!(
+    target_chat.map(|chat| chat.id.clone()),
+    source_chat.map(|chat| chat.id.clone())
+  );
+  assert_eq!(
+    target_chat.and_then(|chat| chat.last_interaction),
+    source_chat.and_then(|chat| chat.last_interaction)
+  );
+
+  let chat = target_chat.map(|chat| chat.id.clone()).unwrap_or_default();
+  let messages = target.list_messages(chat, None, 10).await?.records;
+  let contents = messages
+    .iter()
+    .map(|message| message.content.clone())
+    .collect::<Vec<_>>();
+  assert_eq!(contents, vec!["hi".to_string(), "hello".to_string()]);
+
+  let message = messages
+    .first()
+    .map(|message| message.id.clone())
+    .unwrap_or_default();
+  let files = target
+    .list_files_for_message(message)
+    .await?
+    .into_iter()
+    .map(|file| file.data)
+    .collect::<Vec<_>>();
+  assert_eq!(files, vec![vec![0, 1, 2, 255]]);
+
+  let private = target.private_store();
+  let memos = private.list_memos().await?;
+  let entries = private
+    .list_journal_entries()
+    .await?
+    .into_iter()
+    .map(|entry| entry.content)
+    .collect::<Vec<_>>();
+  let memo = memos.first().map(|memo| memo.id.clone()).unwrap_or_default();
+  let memo_files = private
+    .list_files_for_memo(memo)
+    .await?
+    .into_iter()
+    .map(|file| file.data)
+    .collect::<Vec<_>>();
+  assert_eq!(memos.len(), 1);
+  assert_eq!(entries, vec!["secret diary".to_string()]);
+  assert_eq!(memo_files, vec![vec![42]]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_import_without_private() -> anyhow::Result<()> {
+  let source = common::setup().await?;
+  seed(&source).await?;
+
+  let mut archive = Vec::new();
+  let _ = source.export(&mut archive, true).await?;
+
+  let target = common::setup().await?;
+  let imported = target.import(archive.as_slice(), false).await?;
+
+  assert_eq!(imported.messages, 2);
+  assert_eq!(imported.files, 1);
+  assert_eq!(imported.memos, 0);
+  assert_eq!(imported.skipped, 4);
+  assert!(target.private_store().list_memos().await?.is_empty());
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_export_skips_orphan_messages() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let archive = [
+    r#"{"kind":"header","version":1,"private":false}"#,
+    r#"{"kind":"message","id":"orphan","chat":"missing","#,
+    r#""timestamp":"2024-01-01T00:00:00Z","sender":"user","#,
+    r#""content":"hi","embedding":null}"#,
+  ];
+  let archive = format!("{}\n{}\n", archive[0], archive[1..].concat());
+  let _ = client.import(archive.as_bytes(), false).await?;
+
+  let mut exported = Vec::new();
+  let summary = client.export(&mut exported, false).await?;
+
+  assert_eq!(summary.messages, 0);
+  assert_eq!(summary.skipped, 1);
+  assert!(!String::from_utf8(exported)?.contains("orphan"));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_import_newer_archive() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let archive = r#"{"kind":"header","version":999,"private":false}"#;
+  let result = client.import(archive.as_bytes(), false).await;
+
+  assert!(matches!(result, Err(nebulon::Error::Archive(_))));
+
+  Ok(())
+}
@@ -0,0 +1,108 @@
+mod common;
+
+#[tokio::test]
+async fn test_list_chats() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let quiet = client.insert_chat().await?;
+  let active = client.insert_chat().await?;
+  let _ = client
+    .insert_message(active.id.clone(), "sender".to_string(), "hi".to_string())
+    .await?;
+
+  let result = client
+    .list_chats()
+    .await?
+    .into_iter()
+    .map(|chat| chat.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![active.id, quiet.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_get_chat() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+
+  let found = client.get_chat(chat.id.clone()).await?.map(|chat| chat.id);
+  let missing = client.get_chat("missing".to_string()).await?;
+
+  assert_eq!(found, Some(chat.id));
+  assert!(missing.is_none());
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_list_messages() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let other = client.insert_chat().await?;
+  let mut contents = Vec::new();
+  for index in 0..3 {
+    let content = format!("message {index}");
+    let _ = client
+      .insert_message(chat.id.clone(), "sender".to_string(), content.clone())
+      .await?;
+    contents.push(content);
+  }
+  let _ = client
+    .insert_message(other.id, "sender".to_string(), "other".to_string())
+    .await?;
+
+  let first = client.list_messages(chat.id.clone(), None, 2).await?;
+  let second = client.list_messages(chat.id, first.next.clone(), 2).await?;
+
+  let result = first
+    .records
+    .into_iter()
+    .chain(second.records)
+    .map(|message| message.content)
+    .collect::<Vec<_>>();
+
+  assert!(first.next.is_some());
+  assert!(second.next.is_none());
+  assert_eq!(result, contents);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_chat() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let other = client.insert_chat().await?;
+  let _ = client
+    .insert_message(chat.id.clone(), "sender".to_string(), "gone".to_string())
+    .await?;
+  let _ = client
+    .insert_message(other.id.clone(), "sender".to_string(), "kept".to_string())
+    .await?;
+
+  client.delete_chat(chat.id.clone()).await?;
+
+  let chats = client
+    .list_chats()
+    .await?
+    .into_iter()
+    .map(|chat| chat.id)
+    .collect::<Vec<_>>();
+  let searched = client
+    .search_messages("gone")
+    .await?
+    .into_iter()
+    .chain(client.search_messages("kept").await?)
+    .map(|result| result.record.content)
+    .collect::<Vec<_>>();
+
+  assert_eq!(chats, vec![other.id]);
+  assert_eq!(searched, vec!["kept".to_string()]);
+
+  Ok(())
+}
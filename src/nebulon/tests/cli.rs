@@ -0,0 +1,114 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const ARCHIVE: &str = concat!(
+  r#"{"kind":"header","version":1,"private":false}"#,
+  "\n",
+  r#"{"kind":"chat","id":"chat","timestamp":"2024-01-01T00:00:00Z","#,
+  r#""last_interaction":"2024-01-01T00:01:00Z"}"#,
+  "\n",
+  r#"{"kind":"message","id":"message","chat":"chat","#,
+  r#""timestamp":"2024-01-01T00:01:00Z","sender":"user","#,
+  r#""content":"hello","embedding":[1.0,0.0,0.0]}"#,
+  "\n",
+);
+
+fn nebulon(path: &std::path::Path, dimension: usize, args: &[&str]) -> Command {
+  let mut command = Command::new(env!("CARGO_BIN_EXE_nebulon"));
+  command
+    .args(args)
+    .env("NEBULON_EMBEDDING_DIMENSION", dimension.to_string())
+    .env("NEBULON_CONNECTION", "surrealkv")
+    .env("NEBULON_PATH", path)
+    .env("NEBULON_LOG_LEVEL", "debug")
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+  command
+}
+
+fn import(
+  path: &std::path::Path,
+  dimension: usize,
+  archive: &[u8],
+) -> anyhow::Result<()> {
+  let mut child = nebulon(path, dimension, &["import"]).spawn()?;
+  if let Some(mut stdin) = child.stdin.take() {
+    stdin.write_all(archive)?;
+  }
+  let output = child.wait_with_output()?;
+  anyhow::ensure!(
+    output.status.success(),
+    "import failed: {}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  Ok(())
+}
+
+fn migrate(path: &std::path::Path, dimension: usize) -> anyhow::Result<()> {
+  let output = nebulon(path, dimension, &["migrate", "up"]).output()?;
+  anyhow::ensure!(
+    output.status.success(),
+    "migrate failed: {}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  Ok(())
+}
+
+fn export(path: &std::path::Path, dimension: usize) -> anyhow::Result<Vec<u8>> {
+  let output = nebulon(path, dimension, &["export"]).output()?;
+  anyhow::ensure!(
+    output.status.success(),
+    "export failed: {}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  Ok(output.stdout)
+}
+
+#[test]
+fn test_cli_export_import_round_trip() -> anyhow::Result<()> {
+  let dir =
+    std::env::temp_dir().join(format!("nebulon-cli-{}", ulid::Ulid::new()));
+  let source = dir.join("source");
+  let target = dir.join("target");
+
+  let result = (|| {
+    import(&source, 3, ARCHIVE.as_bytes())?;
+    let exported = export(&source, 3)?;
+    import(&target, 3, &exported)?;
+    let reexported = export(&target, 3)?;
+
+    for line in String::from_utf8(exported.clone())?.lines() {
+      let _ = serde_json::from_str::<serde_json::Value>(line)?;
+    }
+    assert_eq!(exported, reexported);
+    assert_eq!(String::from_utf8(exported)?.lines().count(), 3);
+
+    Ok(())
+  })();
+
+  let _ = std::fs::remove_dir_all(&dir);
+  result
+}
+
+#[test]
+fn test_cli_embedding_dimension_change() -> anyhow::Result<()> {
+  let dir =
+    std::env::temp_dir().join(format!("nebulon-cli-{}", ulid::Ulid::new()));
+
+  let result = (|| {
+    import(&dir, 3, ARCHIVE.as_bytes())?;
+    migrate(&dir, 3)?;
+    let unchanged = String::from_utf8(export(&dir, 3)?)?;
+    migrate(&dir, 4)?;
+    let changed = String::from_utf8(export(&dir, 4)?)?;
+
+    assert!(unchanged.contains(r#""embedding":[1.0,0.0,0.0]"#));
+    assert!(changed.contains(r#""embedding":null"#));
+
+    Ok(())
+  })();
+
+  let _ = std::fs::remove_dir_all(&dir);
+  result
+}
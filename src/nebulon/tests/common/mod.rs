@@ -2,6 +2,7 @@ pub async fn setup() -> anyhow::Result<nebulon::client::Client> {
   let client = nebulon::client::connect(nebulon::config::ClientConfig {
     auth: Default::default(),
     connection: nebulon::config::ConnectionConfig::Memory,
+    ..Default::default()
   })
   .await?;
 
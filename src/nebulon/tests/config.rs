@@ -0,0 +1,136 @@
+// NOTE: every test uses its own prefix because tests run in parallel
+
+fn from_env(
+  prefix: &str,
+  vars: &[(&str, &str)],
+) -> anyhow::Result<nebulon::config::FromEnv> {
+  for (name, value) in vars {
+    std::env::set_var(format!("{prefix}_{name}"), value);
+  }
+  gravity::config::from_env(prefix)
+}
+
+#[test]
+fn test_env_defaults() -> anyhow::Result<()> {
+  let env = from_env("NEBULON_TEST_DEFAULTS", &[])?;
+
+  assert_eq!(env.client.namespace, "double_star");
+  assert_eq!(env.client.auth.user, "double_star");
+  assert_eq!(env.client.embedding_dimension, 384);
+  assert!(matches!(
+    env.client.connection,
+    nebulon::config::ConnectionConfig::Websocket(
+      nebulon::config::WebsocketConnectionConfig { port: 8000, .. }
+    )
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn test_env_numbers() -> anyhow::Result<()> {
+  let env = from_env(
+    "NEBULON_TEST_NUMBERS",
+    &[
+      ("EMBEDDING_DIMENSION", "512"),
+      ("CONNECT_TIMEOUT", "1"),
+      ("QUERY_TIMEOUT", "2"),
+      ("HEALTH_CHECK_INTERVAL", "3"),
+      ("RECONNECT_BACKOFF", "4"),
+      ("RECONNECT_BACKOFF_MAX", "5"),
+      ("PORT", "8001"),
+      ("TLS", "true"),
+    ],
+  )?;
+
+  assert_eq!(env.client.embedding_dimension, 512);
+  assert_eq!(env.client.connect_timeout, 1);
+  assert_eq!(env.client.query_timeout, 2);
+  assert_eq!(env.client.health_check_interval, 3);
+  assert_eq!(env.client.reconnect_backoff, 4);
+  assert_eq!(env.client.reconnect_backoff_max, 5);
+  assert!(matches!(
+    env.client.connection,
+    nebulon::config::ConnectionConfig::Websocket(
+      nebulon::config::WebsocketConnectionConfig {
+        port: 8001,
+        tls: true,
+        ..
+      }
+    )
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn test_env_connection() -> anyhow::Result<()> {
+  let env = from_env(
+    "NEBULON_TEST_CONNECTION",
+    &[("CONNECTION", "surrealkv"), ("PATH", "/tmp/nebulon")],
+  )?;
+
+  match env.client.connection {
+    nebulon::config::ConnectionConfig::SurrealKv(embedded) => {
+      assert_eq!(embedded.path, Some("/tmp/nebulon".into()));
+    }
+    _ => anyhow::bail!("expected a surrealkv connection"),
+  }
+
+  Ok(())
+}
+
+#[test]
+fn test_env_rocksdb_connection() -> anyhow::Result<()> {
+  let env = from_env(
+    "NEBULON_TEST_ROCKSDB_CONNECTION",
+    &[("CONNECTION", "rocksdb"), ("PATH", "/tmp/nebulon")],
+  )?;
+
+  match env.client.connection {
+    nebulon::config::ConnectionConfig::RocksDb(embedded) => {
+      assert_eq!(embedded.path, Some("/tmp/nebulon".into()));
+    }
+    _ => anyhow::bail!("expected a rocksdb connection"),
+  }
+
+  Ok(())
+}
+
+#[test]
+fn test_env_invalid_number() {
+  let env = from_env(
+    "NEBULON_TEST_INVALID_NUMBER",
+    &[("CONNECTION", "memory"), ("QUERY_TIMEOUT", "soon")],
+  );
+
+  assert!(env.is_err());
+}
+
+#[test]
+fn test_env_invalid_connection() {
+  let env =
+    from_env("NEBULON_TEST_INVALID_CONNECTION", &[("CONNECTION", "memry")]);
+
+  assert!(env.is_err());
+}
+
+#[test]
+fn test_env_unused_variable() {
+  let env = from_env(
+    "NEBULON_TEST_UNUSED_VARIABLE",
+    &[("CONNECTION", "memory"), ("HOST", "localhost")],
+  );
+
+  assert!(env.is_err());
+}
+
+#[test]
+fn test_env_ca_without_tls() {
+  let env = from_env(
+    "NEBULON_TEST_CA_WITHOUT_TLS",
+    &[("TLS", "false"), ("CA", "/tmp/ca.pem")],
+  );
+
+  assert!(env.is_err());
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use revision::Revisioned;
+use surrealdb_core::{
+  dbs::Session,
+  kvs::Datastore,
+  rpc::{format::Format, method::Method, BasicRpcContext, Data, RpcContext},
+  sql::Value,
+};
+use tokio_tungstenite::tungstenite::{
+  handshake::server::{ErrorResponse, Request, Response as HandshakeResponse},
+  http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderValue},
+  Message,
+};
+
+/// Serves the database RPC protocol from an in-memory datastore so tests can
+/// cut the connection without a database server
+struct Server {
+  listener: tokio::task::JoinHandle<()>,
+  connections: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>>,
+}
+
+#[revision::revisioned(revision = 1)]
+#[derive(serde::Serialize)]
+struct Response {
+  id: Option<Value>,
+  result: Result<Data, Failure>,
+}
+
+#[revision::revisioned(revision = 1)]
+#[derive(serde::Serialize)]
+struct Failure {
+  code: i64,
+  message: String,
+}
+
+impl Server {
+  async fn start(port: u16, kvs: Arc<Datastore>) -> anyhow::Result<Self> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    let connections = Arc::new(Mutex::new(Vec::new()));
+
+    let listener = {
+      let connections = connections.clone();
+      tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+          let connection = tokio::spawn(serve(stream, kvs.clone()));
+          if let Ok(mut connections) = connections.lock() {
+            connections.push(connection);
+          }
+        }
+      })
+    };
+
+    Ok(Self {
+      listener,
+      connections,
+    })
+  }
+
+  fn stop(self) {
+    self.listener.abort();
+    if let Ok(connections) = self.connections.lock() {
+      for connection in connections.iter() {
+        connection.abort();
+      }
+    }
+  }
+}
+
+/// The client only speaks the revision format
+#[allow(clippy::result_large_err, reason = "tungstenite callback signature")]
+fn protocol(
+  _: &Request,
+  mut response: HandshakeResponse,
+) -> Result<HandshakeResponse, ErrorResponse> {
+  response
+    .headers_mut()
+    .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("revision"));
+  Ok(response)
+}
+
+async fn serve(stream: tokio::net::TcpStream, kvs: Arc<Datastore>) {
+  let Ok(socket) = tokio_tungstenite::accept_hdr_async(stream, protocol).await
+  else {
+    return;
+  };
+  let (mut sink, mut stream) = socket.split();
+
+  let mut context = BasicRpcContext::new(
+    &kvs,
+    Session::default(),
+    Default::default(),
+    "surrealdb-2.0.4".to_string(),
+  );
+  while let Some(Ok(message)) = stream.next().await {
+    let Message::Binary(binary) = message else {
+      continue;
+    };
+    let Ok(request) = Format::Revision.req(binary) else {
+      continue;
+    };
+    let result = context
+      .execute(Method::parse(request.method), request.params)
+      .await
+      .map_err(|err| Failure {
+        code: -32000,
+        message: err.to_string(),
+      });
+
+    let mut response = Vec::new();
+    if (Response {
+      id: request.id,
+      result,
+    })
+    .serialize_revisioned(&mut response)
+    .is_err()
+    {
+      return;
+    }
+    if sink.send(Message::Binary(response)).await.is_err() {
+      return;
+    }
+  }
+}
+
+async fn free_port() -> anyhow::Result<u16> {
+  let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+  Ok(listener.local_addr()?.port())
+}
+
+#[tokio::test]
+async fn test_memory_health() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  client.health().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_websocket_reconnect() -> anyhow::Result<()> {
+  let kvs = Arc::new(Datastore::new("memory").await?.with_auth_enabled(true));
+  kvs
+    .execute(
+      "DEFINE USER double_star ON ROOT PASSWORD 'double_star' ROLES OWNER;",
+      &Session::owner(),
+      None,
+    )
+    .await?;
+  let port = free_port().await?;
+  let server = Server::start(port, kvs.clone()).await?;
+
+  let client = nebulon::client::connect(nebulon::config::ClientConfig {
+    connection: nebulon::config::ConnectionConfig::Websocket(
+      nebulon::config::WebsocketConnectionConfig {
+        host: "127.0.0.1".to_string(),
+        port: port.into(),
+        ..Default::default()
+      },
+    ),
+    connect_timeout: 5,
+    query_timeout: 5,
+    health_check_interval: 1,
+    reconnect_backoff: 50,
+    reconnect_backoff_max: 200,
+    ..Default::default()
+  })
+  .await?;
+  client.migrate().await?;
+  let chat = client.insert_chat().await?;
+
+  server.stop();
+  assert!(client.health().await.is_err());
+
+  let server = Server::start(port, kvs).await?;
+  let mut healthy = false;
+  for _ in 0..50 {
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    if client.health().await.is_ok() {
+      healthy = true;
+      break;
+    }
+  }
+  assert!(healthy);
+  assert!(client.get_chat(chat.id).await?.is_some());
+
+  server.stop();
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_zero_health_check_interval() -> anyhow::Result<()> {
+  let result = nebulon::client::connect(nebulon::config::ClientConfig {
+    connection: nebulon::config::ConnectionConfig::Memory,
+    health_check_interval: 0,
+    ..Default::default()
+  })
+  .await;
+
+  assert!(matches!(result, Err(nebulon::Error::Config(_))));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_record_auth_needs_access() -> anyhow::Result<()> {
+  let result = nebulon::client::connect(nebulon::config::ClientConfig {
+    auth: nebulon::config::AuthConfig {
+      auth_level: nebulon::config::AuthLevel::Record,
+      access: None,
+      ..Default::default()
+    },
+    ..Default::default()
+  })
+  .await;
+
+  assert!(matches!(result, Err(nebulon::Error::Config(_))));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_pass_file() -> anyhow::Result<()> {
+  let result = nebulon::client::connect(nebulon::config::ClientConfig {
+    auth: nebulon::config::AuthConfig {
+      pass_file: Some(std::path::PathBuf::from("/nonexistent/pass")),
+      ..Default::default()
+    },
+    ..Default::default()
+  })
+  .await;
+
+  assert!(matches!(result, Err(nebulon::Error::Config(_))));
+
+  Ok(())
+}
@@ -0,0 +1,96 @@
+use surrealdb::error::{Api, Db};
+
+fn remote(err: Db) -> nebulon::Error {
+  surrealdb::Error::Api(Api::Query(err.to_string())).into()
+}
+
+fn thing() -> surrealdb::sql::Thing {
+  surrealdb::sql::Thing::from(("file", "id"))
+}
+
+#[test]
+fn test_local_errors() {
+  assert!(matches!(
+    nebulon::Error::from(surrealdb::Error::Db(Db::InvalidAuth)),
+    nebulon::Error::Auth(_)
+  ));
+  assert!(matches!(
+    nebulon::Error::from(surrealdb::Error::Db(Db::RecordExists {
+      thing: thing()
+    })),
+    nebulon::Error::Constraint(_)
+  ));
+  assert!(matches!(
+    nebulon::Error::from(surrealdb::Error::Api(Api::Ws(
+      "closed".to_string()
+    ))),
+    nebulon::Error::Connection(_)
+  ));
+  assert!(matches!(
+    nebulon::Error::from(surrealdb::Error::Api(Api::Query(
+      "Parse error".to_string()
+    ))),
+    nebulon::Error::Database(_)
+  ));
+}
+
+#[test]
+fn test_remote_invalid_auth() {
+  assert!(matches!(remote(Db::InvalidAuth), nebulon::Error::Auth(_)));
+}
+
+#[test]
+fn test_remote_expired_session() {
+  assert!(matches!(remote(Db::ExpiredSession), nebulon::Error::Auth(_)));
+}
+
+#[test]
+fn test_remote_expired_token() {
+  assert!(matches!(remote(Db::ExpiredToken), nebulon::Error::Auth(_)));
+}
+
+#[test]
+fn test_remote_record_exists() {
+  assert!(matches!(
+    remote(Db::RecordExists { thing: thing() }),
+    nebulon::Error::Constraint(_)
+  ));
+}
+
+#[test]
+fn test_remote_index_exists() {
+  assert!(matches!(
+    remote(Db::IndexExists {
+      thing: thing(),
+      index: "file_title".to_string(),
+      value: "'title'".to_string(),
+    }),
+    nebulon::Error::Constraint(_)
+  ));
+}
+
+#[test]
+fn test_remote_field_check() {
+  assert!(matches!(
+    remote(Db::FieldCheck {
+      thing: thing().to_string(),
+      value: "1".to_string(),
+      field: "title".into(),
+      check: "string".to_string(),
+    }),
+    nebulon::Error::Constraint(_)
+  ));
+}
+
+#[test]
+fn test_remote_field_value() {
+  assert!(matches!(
+    remote(Db::FieldValue {
+      thing: thing().to_string(),
+      value: "''".to_string(),
+      field: "title".into(),
+      check: "$value != ''".to_string(),
+    }),
+    nebulon::Error::Constraint(_)
+  ));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+#[tokio::test]
+async fn test_file_attachments() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let message = client
+    .insert_message(chat.id, "sender".to_string(), "see file".to_string())
+    .await?;
+  let file = client
+    .insert_file(
+      "notes".to_string(),
+      "txt".to_string(),
+      "some notes".to_string(),
+      b"hello".to_vec(),
+    )
+    .await?;
+  let _ = client
+    .insert_file(
+      "unrelated".to_string(),
+      "txt".to_string(),
+      "not attached".to_string(),
+      Vec::new(),
+    )
+    .await?;
+
+  client
+    .attach_file_to_message(file.id.clone(), message.id.clone())
+    .await?;
+
+  let attached = client
+    .list_files_for_message(message.id)
+    .await?
+    .into_iter()
+    .map(|file| file.id)
+    .collect::<Vec<_>>();
+  let found = client.get_file(file.id.clone()).await?.map(|file| file.data);
+
+  assert_eq!(attached, vec![file.id]);
+  assert_eq!(found, Some(b"hello".to_vec()));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_file_search() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let _ = client
+    .insert_file(
+      "quarterly report".to_string(),
+      "pdf".to_string(),
+      "numbers".to_string(),
+      Vec::new(),
+    )
+    .await?;
+
+  let result = client
+    .search_files("report")
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec!["quarterly <b>report</b>".to_string()]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_file_description_search() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let _ = client
+    .insert_file(
+      "notes".to_string(),
+      "txt".to_string(),
+      "meeting report".to_string(),
+      Vec::new(),
+    )
+    .await?;
+  let _ = client
+    .insert_file(
+      "other".to_string(),
+      "txt".to_string(),
+      "unrelated".to_string(),
+      Vec::new(),
+    )
+    .await?;
+
+  let result = client
+    .search_files("report")
+    .await?
+    .into_iter()
+    .map(|result| (result.record.title, result.highlights))
+    .collect::<Vec<_>>();
+
+  assert_eq!(
+    result,
+    vec![("notes".to_string(), "meeting <b>report</b>".to_string())]
+  );
+
+  Ok(())
+}
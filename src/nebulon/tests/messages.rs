@@ -27,3 +27,95 @@ async fn test_message_search() -> anyhow::Result<()> {
 
   Ok(())
 }
+
+#[tokio::test]
+async fn test_update_message_content() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let message = client
+    .insert_message(chat.id.clone(), "sender".to_string(), "typo".to_string())
+    .await?;
+
+  client
+    .set_message_embedding(
+      message.id.clone(),
+      vec![0.0; client.embedding_dimension()],
+    )
+    .await?;
+  let embedded = client.list_unembedded_messages(10).await?;
+  let updated = client
+    .update_message_content(message.id.clone(), "fixed".to_string())
+    .await?;
+  let unembedded = client
+    .list_unembedded_messages(10)
+    .await?
+    .into_iter()
+    .map(|message| message.id)
+    .collect::<Vec<_>>();
+  let listed = client
+    .list_messages(chat.id, None, 10)
+    .await?
+    .records
+    .into_iter()
+    .map(|message| message.content)
+    .collect::<Vec<_>>();
+
+  assert_eq!(updated.id, message.id);
+  assert_eq!(updated.content, "fixed");
+  assert_eq!(listed, vec!["fixed".to_string()]);
+  assert!(embedded.is_empty());
+  assert_eq!(unembedded, vec![message.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_message() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let kept = client
+    .insert_message(chat.id.clone(), "sender".to_string(), "kept".to_string())
+    .await?;
+  let deleted = client
+    .insert_message(chat.id.clone(), "sender".to_string(), "gone".to_string())
+    .await?;
+
+  client.delete_message(deleted.id).await?;
+
+  let listed = client
+    .list_messages(chat.id.clone(), None, 10)
+    .await?
+    .records
+    .into_iter()
+    .map(|message| message.id)
+    .collect::<Vec<_>>();
+  let searched = client.search_messages("gone").await?;
+  let chat = client.get_chat(chat.id).await?;
+
+  assert_eq!(listed, vec![kept.id]);
+  assert!(searched.is_empty());
+  assert_eq!(chat.and_then(|chat| chat.last_interaction), Some(kept.timestamp));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_update_missing_message() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let result = client
+    .update_message_content("missing".to_string(), "content".to_string())
+    .await;
+
+  assert!(matches!(
+    result,
+    Err(nebulon::Error::NotFound {
+      table: "message",
+      ..
+    })
+  ));
+
+  Ok(())
+}
@@ -0,0 +1,130 @@
+mod common;
+
+#[tokio::test]
+async fn test_migration_status_after_migrate() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let status = client.migration_status().await?;
+
+  assert!(!status.is_empty());
+  assert!(status.iter().all(|status| status.applied && status.known));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_down_and_up() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  client
+    .migrate_down(nebulon::migration::INITIAL_MIGRATION)
+    .await?;
+  let reverted = client.migration_status().await?;
+  client.migrate_up(None).await?;
+  let applied = client.migration_status().await?;
+
+  assert!(!reverted.is_empty());
+  assert!(reverted.iter().all(|status| !status.applied && status.known));
+  assert!(applied.iter().all(|status| status.applied && status.known));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_to_migration_of_another_store() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+  let to = "20261018_000001_AddFileDescriptionSearch";
+
+  client.migrate_down(to).await?;
+  let reverted = client.migration_status().await?;
+  client
+    .migrate_down(nebulon::migration::INITIAL_MIGRATION)
+    .await?;
+  client.migrate_up(Some(to)).await?;
+  let applied = client.migration_status().await?;
+
+  for status in [reverted, applied] {
+    assert!(status.iter().any(|status| {
+      status.store == nebulon::migration::MigrationStore::Private
+    }));
+    assert!(status
+      .iter()
+      .all(|status| status.applied == (status.name.as_str() <= to)));
+  }
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_down_removes_embedding_indexes() -> anyhow::Result<()> {
+  let dir =
+    std::env::temp_dir().join(format!("nebulon-down-{}", ulid::Ulid::new()));
+
+  let result = async {
+    // NOTE: the client is dropped first because an embedded store can only
+    // be opened once
+    {
+      let client = nebulon::client::connect(nebulon::config::ClientConfig {
+        auth: Default::default(),
+        connection: nebulon::config::ConnectionConfig::SurrealKv(
+          nebulon::config::EmbeddedConnectionConfig {
+            path: Some(dir.clone()),
+          },
+        ),
+        ..Default::default()
+      })
+      .await?;
+      client.migrate().await?;
+      client
+        .migrate_down("20261018_000001_AddFileDescriptionSearch")
+        .await?;
+    }
+
+    for (database, tables) in
+      [("private", ["file", "memo"]), ("public", ["file", "message"])]
+    {
+      let db = surrealdb::engine::any::connect(format!(
+        "surrealkv:{}/{database}",
+        dir.display()
+      ))
+      .await?;
+      db.use_ns("double_star").use_db(database).await?;
+
+      let mut response = db.query("INFO FOR DB").await?;
+      let info: Option<serde_json::Value> = response.take(0)?;
+      let info = info.unwrap_or_default();
+      assert!(info["tables"].is_object());
+      assert!(info["tables"].get("embedding_index").is_none());
+
+      for table in tables {
+        let mut response = db.query(format!("INFO FOR TABLE {table}")).await?;
+        let info: Option<serde_json::Value> = response.take(0)?;
+        let info = info.unwrap_or_default();
+        assert!(info["indexes"].is_object());
+        assert!(info["indexes"].get(format!("{table}_embedding")).is_none());
+      }
+    }
+
+    Ok(())
+  }
+  .await;
+
+  let _ = std::fs::remove_dir_all(&dir);
+  result
+}
+
+#[tokio::test]
+async fn test_migrate_to_unknown_migration() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  assert!(matches!(
+    client.migrate_up(Some("unknown")).await,
+    Err(nebulon::Error::Migration(_))
+  ));
+  assert!(matches!(
+    client.migrate_down("unknown").await,
+    Err(nebulon::Error::Migration(_))
+  ));
+
+  Ok(())
+}
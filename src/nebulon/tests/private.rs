@@ -0,0 +1,89 @@
+mod common;
+
+#[tokio::test]
+async fn test_memo_search() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+  let private = client.private_store();
+
+  let _ = private.insert_memo("secret plan".to_string()).await?;
+  let _ = private.insert_journal_entry("secret diary".to_string()).await?;
+
+  let memos = private
+    .search_memos("secret")
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+  let entries = private
+    .search_journal_entries("secret")
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+
+  assert_eq!(memos, vec!["<b>secret</b> plan".to_string()]);
+  assert_eq!(entries, vec!["<b>secret</b> diary".to_string()]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_memo_search_highlight_markers() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+  let private = client.private_store();
+
+  let _ = private.insert_memo("secret plan".to_string()).await?;
+  let _ = private.insert_journal_entry("secret diary".to_string()).await?;
+
+  let query = nebulon::search::SearchQuery::new("secret").highlight("[", "]");
+  let memos = private
+    .search_memos(query.clone())
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+  let entries = private
+    .search_journal_entries(query)
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+
+  assert_eq!(memos, vec!["[secret] plan".to_string()]);
+  assert_eq!(entries, vec!["[secret] diary".to_string()]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_private_is_not_public() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+  let private = client.private_store();
+
+  let memo = private.insert_memo("secret plan".to_string()).await?;
+  let file = private
+    .insert_file(
+      "secret".to_string(),
+      "txt".to_string(),
+      "hidden".to_string(),
+      Vec::new(),
+    )
+    .await?;
+  private
+    .attach_file_to_memo(file.id.clone(), memo.id.clone())
+    .await?;
+
+  let attached = private
+    .list_files_for_memo(memo.id)
+    .await?
+    .into_iter()
+    .map(|file| file.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(attached, vec![file.id.clone()]);
+  assert!(client.search_messages("secret").await?.is_empty());
+  assert!(client.search_files("secret").await?.is_empty());
+  assert!(client.get_file(file.id).await?.is_none());
+
+  Ok(())
+}
@@ -0,0 +1,279 @@
+mod common;
+
+fn unit(client: &nebulon::client::Client, index: usize) -> Vec<f32> {
+  let mut embedding = vec![0.0; client.embedding_dimension()];
+  embedding[index] = 1.0;
+  embedding
+}
+
+#[tokio::test]
+async fn test_hybrid_search_fuses_rankings() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let both = client
+    .insert_message(
+      chat.id.clone(),
+      "sender".to_string(),
+      "rust borrow checker".to_string(),
+    )
+    .await?;
+  let lexical = client
+    .insert_message(
+      chat.id.clone(),
+      "sender".to_string(),
+      "rust compiler".to_string(),
+    )
+    .await?;
+  let semantic = client
+    .insert_message(chat.id, "sender".to_string(), "lifetimes".to_string())
+    .await?;
+  client
+    .set_message_embedding(both.id.clone(), unit(&client, 0))
+    .await?;
+  client
+    .set_message_embedding(lexical.id.clone(), unit(&client, 1))
+    .await?;
+  client
+    .set_message_embedding(semantic.id.clone(), unit(&client, 0))
+    .await?;
+
+  let result = client
+    .hybrid_search_messages("rust", unit(&client, 0), Default::default(), 10)
+    .await?;
+
+  let first = result.first().ok_or_else(|| anyhow::anyhow!("No results"))?;
+  assert_eq!(first.record.id, both.id);
+  assert!(first.lexical_rank.is_some());
+  assert!(first.semantic_rank.is_some());
+  assert_eq!(result.len(), 3);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_hybrid_search_filters() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let other_chat = client.insert_chat().await?;
+  let kept = client
+    .insert_message(chat.id.clone(), "user".to_string(), "rust".to_string())
+    .await?;
+  let other_sender = client
+    .insert_message(chat.id.clone(), "agent".to_string(), "rust".to_string())
+    .await?;
+  let elsewhere = client
+    .insert_message(other_chat.id, "user".to_string(), "rust".to_string())
+    .await?;
+  for message in [&kept, &other_sender, &elsewhere] {
+    client
+      .set_message_embedding(message.id.clone(), unit(&client, 0))
+      .await?;
+  }
+
+  let result = client
+    .hybrid_search_messages(
+      "rust",
+      unit(&client, 0),
+      nebulon::search::MessageFilter {
+        chat: Some(chat.id),
+        sender: Some("user".to_string()),
+        after: Some(kept.timestamp),
+        before: None,
+      },
+      10,
+    )
+    .await?
+    .into_iter()
+    .map(|result| result.record.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![kept.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_hybrid_search_filters_beyond_nearest() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let other_chat = client.insert_chat().await?;
+  for _ in 0..8 {
+    let message = client
+      .insert_message(
+        other_chat.id.clone(),
+        "user".to_string(),
+        "lifetimes".to_string(),
+      )
+      .await?;
+    client
+      .set_message_embedding(message.id, unit(&client, 0))
+      .await?;
+  }
+  let far = client
+    .insert_message(chat.id.clone(), "user".to_string(), "traits".to_string())
+    .await?;
+  client
+    .set_message_embedding(far.id.clone(), unit(&client, 1))
+    .await?;
+
+  let result = client
+    .hybrid_search_messages(
+      "rust",
+      unit(&client, 0),
+      nebulon::search::MessageFilter {
+        chat: Some(chat.id),
+        ..Default::default()
+      },
+      1,
+    )
+    .await?
+    .into_iter()
+    .map(|result| result.record.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![far.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_hybrid_search_limit() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  for _ in 0..3 {
+    let message = client
+      .insert_message(chat.id.clone(), "user".to_string(), "rust".to_string())
+      .await?;
+    client
+      .set_message_embedding(message.id, unit(&client, 0))
+      .await?;
+  }
+
+  let result = client
+    .hybrid_search_messages("rust", unit(&client, 0), Default::default(), 2)
+    .await?;
+
+  assert_eq!(result.len(), 2);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_search_pagination() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let mut inserted = Vec::new();
+  for _ in 0..3 {
+    let message = client
+      .insert_message(chat.id.clone(), "user".to_string(), "rust".to_string())
+      .await?;
+    inserted.push(message.id);
+  }
+
+  let query = nebulon::search::SearchQuery::new("rust")
+    .order(nebulon::search::SearchOrder::Oldest)
+    .limit(2);
+  let first = client
+    .search_messages(query.clone())
+    .await?
+    .into_iter()
+    .map(|result| result.record.id)
+    .collect::<Vec<_>>();
+  let second = client
+    .search_messages(query.offset(2))
+    .await?
+    .into_iter()
+    .map(|result| result.record.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(first, inserted[..2].to_vec());
+  assert_eq!(second, inserted[2..].to_vec());
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_search_filters() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let other_chat = client.insert_chat().await?;
+  let kept = client
+    .insert_message(chat.id.clone(), "user".to_string(), "rust".to_string())
+    .await?;
+  let _ = client
+    .insert_message(chat.id.clone(), "agent".to_string(), "rust".to_string())
+    .await?;
+  let _ = client
+    .insert_message(other_chat.id, "user".to_string(), "rust".to_string())
+    .await?;
+
+  let result = client
+    .search_messages(
+      nebulon::search::SearchQuery::new("rust")
+        .chat(chat.id)
+        .sender("user")
+        .after(kept.timestamp),
+    )
+    .await?
+    .into_iter()
+    .map(|result| result.record.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![kept.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_search_highlight_markers() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let _ = client
+    .insert_message(chat.id, "user".to_string(), "some content".to_string())
+    .await?;
+
+  let result = client
+    .search_messages(
+      nebulon::search::SearchQuery::new("some").highlight("[", "]"),
+    )
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec!["[some] content".to_string()]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_search_files_highlight_markers() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let _ = client
+    .insert_file(
+      "some report".to_string(),
+      "txt".to_string(),
+      "quarterly".to_string(),
+      Vec::new(),
+    )
+    .await?;
+
+  let result = client
+    .search_files(nebulon::search::SearchQuery::new("some").highlight("[", "]"))
+    .await?
+    .into_iter()
+    .map(|result| result.highlights)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec!["[some] report".to_string()]);
+
+  Ok(())
+}
@@ -0,0 +1,126 @@
+mod common;
+
+fn unit(client: &nebulon::client::Client, index: usize) -> Vec<f32> {
+  let mut embedding = vec![0.0; client.embedding_dimension()];
+  embedding[index] = 1.0;
+  embedding
+}
+
+#[tokio::test]
+async fn test_nearest_messages() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let near = client
+    .insert_message(chat.id.clone(), "sender".to_string(), "near".to_string())
+    .await?;
+  let far = client
+    .insert_message(chat.id, "sender".to_string(), "far".to_string())
+    .await?;
+  client
+    .set_message_embedding(near.id.clone(), unit(&client, 0))
+    .await?;
+  client
+    .set_message_embedding(far.id.clone(), unit(&client, 1))
+    .await?;
+
+  let result = client.nearest_messages(unit(&client, 0), 2).await?;
+  let ids = result
+    .iter()
+    .map(|result| result.record.id.clone())
+    .collect::<Vec<_>>();
+
+  assert_eq!(ids, vec![near.id, far.id]);
+  assert!(result[0].distance < result[1].distance);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_nearest_files() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let file = client
+    .insert_file(
+      "notes".to_string(),
+      "txt".to_string(),
+      "some notes".to_string(),
+      Vec::new(),
+    )
+    .await?;
+  client.set_file_embedding(file.id.clone(), unit(&client, 2)).await?;
+
+  let result = client
+    .nearest_files(unit(&client, 2), 1)
+    .await?
+    .into_iter()
+    .map(|result| result.record.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![file.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_wrong_embedding_dimension() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let result = client.nearest_messages(vec![1.0], 1).await;
+
+  assert!(matches!(
+    result,
+    Err(nebulon::Error::EmbeddingDimension { actual: 1, .. })
+  ));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_unembedded_messages() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let chat = client.insert_chat().await?;
+  let embedded = client
+    .insert_message(chat.id.clone(), "sender".to_string(), "a".to_string())
+    .await?;
+  let unembedded = client
+    .insert_message(chat.id, "sender".to_string(), "b".to_string())
+    .await?;
+  client
+    .set_message_embedding(embedded.id.clone(), unit(&client, 0))
+    .await?;
+
+  let result = client
+    .list_unembedded_messages(10)
+    .await?
+    .into_iter()
+    .map(|message| message.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![unembedded.id.clone()]);
+
+  client.clear_embeddings().await?;
+
+  let result = client
+    .list_unembedded_messages(10)
+    .await?
+    .into_iter()
+    .map(|message| message.id)
+    .collect::<Vec<_>>();
+
+  assert_eq!(result, vec![embedded.id, unembedded.id]);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_no_neighbours() -> anyhow::Result<()> {
+  let client = common::setup().await?;
+
+  let result = client.nearest_messages(unit(&client, 0), 0).await;
+
+  assert!(matches!(result, Err(nebulon::Error::NoNeighbours)));
+
+  Ok(())
+}
@@ -15,6 +15,7 @@ pub(crate) enum Message {
   Error(String),
   Ok,
   Submit,
+  Cancel,
   ConfigSubmit,
 }
 
@@ -28,6 +29,8 @@ pub(crate) struct Orbitus {
   chat: String,
   input: String,
   error: String,
+  sources: String,
+  generating: bool,
 }
 
 impl Orbitus {
@@ -50,6 +53,8 @@ impl Orbitus {
         chat: "Hello, world!\n".to_string(),
         input: "".to_string(),
         error: "".to_string(),
+        sources: "".to_string(),
+        generating: false,
       },
       Task::none(),
     )
@@ -105,13 +110,31 @@ impl Orbitus {
       Message::Submit => {
         self.chat += self.input.as_str();
         self.chat += "\n";
+        self.sources.clear();
+        self.generating = true;
 
         let tx = self.double_star_tx.clone();
         let mut input = String::new();
         std::mem::swap(&mut input, &mut self.input);
 
         return Task::perform(
-          async move { tx.send_async(gravity::OrbitusMessage::Submit(input)).await },
+          async move {
+            tx.send_async(gravity::OrbitusMessage::Submit(gravity::Prompt {
+              content: input,
+              sampling: Default::default(),
+            }))
+            .await
+          },
+          |result| match result {
+            Ok(_) => Message::Ok,
+            Err(err) => Message::Error(err.to_string()),
+          },
+        );
+      }
+      Message::Cancel => {
+        let tx = self.double_star_tx.clone();
+        return Task::perform(
+          async move { tx.send_async(gravity::OrbitusMessage::Cancel).await },
           |result| match result {
             Ok(_) => Message::Ok,
             Err(err) => Message::Error(err.to_string()),
@@ -119,11 +142,20 @@ impl Orbitus {
         );
       }
       Message::DoubleStar(double_star) => match double_star {
+        gravity::DoubleStarMessage::Retrieved(citations) => {
+          self.sources = citations_to_sources(&citations);
+        }
         gravity::DoubleStarMessage::Generated(generated) => {
           self.chat += generated.as_str();
         }
-        gravity::DoubleStarMessage::Break => {
+        gravity::DoubleStarMessage::Break(reason) => {
+          tracing::debug!("Generation stopped because {:?}", reason);
+          self.chat += "\n";
+          self.generating = false;
+        }
+        gravity::DoubleStarMessage::Cancelled => {
           self.chat += "\n";
+          self.generating = false;
         }
       },
       Message::Config(config) => {
@@ -156,11 +188,14 @@ impl Orbitus {
       .width(Length::Fill);
     let config_submit =
       button(text("Submit config")).on_press(Message::ConfigSubmit);
-    let input_row = row![input, config_submit];
+    let stop = button(text("Stop"))
+      .on_press_maybe(self.generating.then_some(Message::Cancel));
+    let input_row = row![input, stop, config_submit];
 
     let chat = scrollable(text(self.chat.as_str()));
+    let sources = text(self.sources.as_str());
     let error = text(self.error.as_str()).style(danger);
-    let column = column![chat, vertical_space(), error, input_row];
+    let column = column![chat, vertical_space(), sources, error, input_row];
 
     container(container(column).max_width(1024).align_left(Length::Fill))
       .center_x(Length::Fill)
@@ -168,6 +203,26 @@ impl Orbitus {
   }
 }
 
+fn citations_to_sources(citations: &[gravity::Citation]) -> String {
+  let mut sources = "Sources:".to_string();
+  for (index, citation) in citations.iter().enumerate() {
+    let source = match citation.source {
+      gravity::CitationSource::Message => "message",
+      gravity::CitationSource::Memo => "memo",
+    };
+    let excerpt = citation.content.lines().next().unwrap_or_default();
+    sources += format!(
+      "\n[{}] {} {}: {}",
+      index.saturating_add(1),
+      source,
+      citation.id,
+      excerpt
+    )
+    .as_str();
+  }
+  sources
+}
+
 fn palette_to_iced_palette(
   palette: &crate::config::UiPaletteModeConfig,
 ) -> iced::theme::Palette {
@@ -5,6 +5,7 @@ impl gravity::config::FromArgs for FromArgs {}
 
 #[derive(Default, serde::Deserialize)]
 pub struct FromEnv {
+  #[serde(default)]
   pub websocket: WebsocketConfig,
 }
 
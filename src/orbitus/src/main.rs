@@ -13,7 +13,7 @@ fn main() -> anyhow::Result<()> {
     env!("ORGANIZATION"),
     "orbitus",
     concat!(env!("CARGO_PKG_REPOSITORY"), "/src/orbitus"),
-  );
+  )?;
   let config_values = config.values();
   let config_rx = config.subscribe();
 